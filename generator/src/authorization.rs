@@ -0,0 +1,111 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+
+use crate::customers::Customer;
+use crate::payments::{InstrumentBook, Payment};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum DeclineCode {
+    InsufficientFunds,
+    FraudSuspected,
+    Expired,
+    NetworkError,
+}
+
+/// One authorization request sent to the payment network
+#[derive(Debug, Serialize, Clone)]
+pub struct AuthAttempt {
+    pub transaction_id: String,
+    pub instrument_id: String,
+    pub attempted_at: DateTime<Utc>,
+    pub approved: bool,
+    pub decline_code: Option<DeclineCode>,
+}
+
+/// Authorize `total` USD starting with `payment`, retrying the way a checkout
+/// would: network errors are retried on the same instrument, other declines
+/// sometimes lead the customer to try another instrument. Returns every
+/// attempt, oldest first, and the payment used by the last one.
+/// `fraud_risk` is added to the chance an attempt is flagged as fraud.
+pub fn authorize(
+    customer: &Customer,
+    payment: Payment,
+    date: DateTime<Utc>,
+    total: f64,
+    fraud_risk: f64,
+    instruments: &InstrumentBook,
+    rng: &mut impl Rng,
+) -> (Vec<AuthAttempt>, Payment) {
+    let mut attempts: Vec<AuthAttempt> = Vec::new();
+    let mut payment = payment;
+    let mut attempted_at = date;
+
+    loop {
+        let decline_code = decide(&payment, attempted_at, total, fraud_risk, customer, rng);
+        attempts.push(AuthAttempt {
+            transaction_id: payment.transaction_id.clone(),
+            instrument_id: payment.instrument_id.clone(),
+            attempted_at,
+            approved: decline_code.is_none(),
+            decline_code,
+        });
+        let Some(code) = decline_code else { break };
+        if attempts.len() >= MAX_ATTEMPTS {
+            break;
+        }
+        match code {
+            DeclineCode::NetworkError => {
+                attempted_at += Duration::seconds(rng.random_range(5..60));
+                payment = Payment { transaction_id: crate::utils::generate_uuid(rng), ..payment };
+            }
+            _ if rng.random_bool(switch_rate(code)) => {
+                attempted_at += Duration::seconds(rng.random_range(60..1200));
+                payment = instruments.alternate_payment(customer, attempted_at, &payment.instrument_id, rng);
+            }
+            _ => break,
+        }
+    }
+    return (attempts, payment);
+}
+
+/// `None` approves the attempt
+fn decide(
+    payment: &Payment,
+    at: DateTime<Utc>,
+    total: f64,
+    fraud_risk: f64,
+    customer: &Customer,
+    rng: &mut impl Rng,
+) -> Option<DeclineCode> {
+    if at.date_naive() > payment.details.expiration_date() {
+        return Some(DeclineCode::Expired);
+    }
+    let large = total > LARGE_ORDER_USD;
+    let fraud_rate = FRAUD_RATE + fraud_risk + if large { 0.015 } else { 0.0 } + if customer.country != "US" { 0.005 } else { 0.0 };
+    let funds_rate = INSUFFICIENT_FUNDS_RATE + if large { 0.04 } else { 0.0 };
+
+    let roll: f64 = rng.random();
+    if roll < NETWORK_ERROR_RATE { Some(DeclineCode::NetworkError) }
+    else if roll < NETWORK_ERROR_RATE + fraud_rate { Some(DeclineCode::FraudSuspected) }
+    else if roll < NETWORK_ERROR_RATE + fraud_rate + funds_rate { Some(DeclineCode::InsufficientFunds) }
+    else { None }
+}
+
+/// Chance the customer tries another instrument after a decline
+fn switch_rate(code: DeclineCode) -> f64 {
+    return match code {
+        DeclineCode::Expired => 0.6,
+        DeclineCode::InsufficientFunds => 0.5,
+        DeclineCode::FraudSuspected => 0.2,
+        DeclineCode::NetworkError => 1.0,
+    };
+}
+
+// ------------------------ Static Data ------------------------
+
+const MAX_ATTEMPTS: usize = 4;
+const LARGE_ORDER_USD: f64 = 250.0;
+const NETWORK_ERROR_RATE: f64 = 0.01;
+const FRAUD_RATE: f64 = 0.005;
+const INSUFFICIENT_FUNDS_RATE: f64 = 0.02;
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow2::array::{Array, Int64Array, UInt64Array, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, Schema};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::orders::Order;
+use crate::returns::Return;
+use crate::utils;
+use crate::writer;
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CdcFormat {
+    Ndjson,
+    Parquet,
+}
+
+/// A row of the simulated source `customers` table
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CustomerRow {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub address: String,
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub timezone: String,
+    pub status: String,
+}
+
+/// A row of the simulated source `orders` table
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OrderRow {
+    pub id: String,
+    pub customer_id: i64,
+    pub product_id: i64,
+    pub transaction_id: String,
+    pub date: String,
+    pub status: String,
+    pub discount: f64,
+    pub quantity: u32,
+    #[serde(default)]
+    pub subtotal: f64,
+    #[serde(default)]
+    pub discount_amount: f64,
+    #[serde(default)]
+    pub tax: f64,
+    #[serde(default)]
+    pub shipping: f64,
+    pub total: f64,
+    #[serde(default)]
+    pub currency: String,
+    #[serde(default)]
+    pub total_local: f64,
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum Row {
+    Customer(CustomerRow),
+    Order(OrderRow),
+}
+
+#[derive(Debug, Serialize)]
+pub struct Source {
+    pub table: &'static str,
+    pub lsn: u64,
+    pub batch: u64,
+}
+
+/// A Debezium-style change record
+#[derive(Debug, Serialize)]
+pub struct ChangeEvent {
+    pub op: &'static str,
+    pub before: Option<Row>,
+    pub after: Option<Row>,
+    pub source: Source,
+    pub ts_ms: i64,
+}
+
+/// Everything the simulated source database remembers between batches
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct SourceState {
+    batch: u64,
+    lsn: u64,
+    customers: HashMap<i64, CustomerRow>,
+    orders: HashMap<String, OrderRow>,
+    /// When open orders with a pending return are refunded, by order id
+    #[serde(default)]
+    refunds: HashMap<String, String>,
+}
+
+struct ChangeLog {
+    batch: u64,
+    lsn: u64,
+    base_ms: i64,
+    customers: Vec<ChangeEvent>,
+    orders: Vec<ChangeEvent>,
+}
+
+impl ChangeLog {
+    fn push(&mut self, table: &'static str, op: &'static str, before: Option<Row>, after: Option<Row>) {
+        self.lsn += 1;
+        let event = ChangeEvent {
+            op,
+            before,
+            after,
+            source: Source { table, lsn: self.lsn, batch: self.batch },
+            ts_ms: self.base_ms + (self.customers.len() + self.orders.len()) as i64,
+        };
+        if table == "customers" { self.customers.push(event); }
+        else { self.orders.push(event); }
+    }
+}
+
+// ------------------------ Entry Point ------------------------
+
+/// Apply this batch of orders to the simulated source database and write the
+/// resulting change records to `<output_dir>/cdc/`. Refunds still pending on
+/// `returns` are applied by the batch that runs after they're issued.
+/// Returns the files written.
+pub fn emit_changes(
+    orders: &[Order],
+    returns: &[Return],
+    output_dir: &str,
+    format: CdcFormat,
+    retain: usize,
+) -> io::Result<Vec<PathBuf>> {
+    let cdc_dir = Path::new(output_dir).join("cdc");
+    fs::create_dir_all(&cdc_dir)?;
+    let state_path = cdc_dir.join("state.json");
+    let mut state = load_state(&state_path)?;
+    state.batch += 1;
+
+    let mut log = ChangeLog {
+        batch: state.batch,
+        lsn: state.lsn,
+        base_ms: Utc::now().timestamp_millis(),
+        customers: Vec::new(),
+        orders: Vec::new(),
+    };
+    advance_open_orders(&mut state, &mut log);
+    insert_batch(&mut state, &mut log, orders, returns);
+    trim_open_orders(&mut state, retain);
+    state.refunds.retain(|id, _| state.orders.contains_key(id));
+    state.lsn = log.lsn;
+
+    let suffix = format!("{:06}", state.batch);
+    let mut written = Vec::with_capacity(3);
+    for (table, events) in [("customers", &log.customers), ("orders", &log.orders)] {
+        let path = match format {
+            CdcFormat::Ndjson => cdc_dir.join(format!("{table}-{suffix}.ndjson")),
+            CdcFormat::Parquet => cdc_dir.join(format!("{table}-{suffix}.parquet")),
+        };
+        match format {
+            CdcFormat::Ndjson => write_ndjson(events, &path)?,
+            CdcFormat::Parquet => write_events_parquet(events, &path)?,
+        }
+        written.push(path);
+    }
+    save_state(&state_path, &state)?;
+    written.push(state_path);
+    println!(
+        "Captured {} customer and {} order changes (batch {}).",
+        log.customers.len(), log.orders.len(), state.batch
+    );
+    return Ok(written);
+}
+
+// ------------------------ Source Simulation ------------------------
+
+/// Move orders left open by earlier batches along their lifecycle:
+/// pending orders complete once their date passes, backordered ones once a
+/// restock fills them, completed orders are refunded once a pending return's
+/// refund is due, and a few rows are deleted outright.
+fn advance_open_orders(state: &mut SourceState, log: &mut ChangeLog) {
+    let mut rng = rand::rng();
+    let now = Utc::now().to_rfc3339();
+    let mut ids: Vec<String> = state.orders.keys().cloned().collect();
+    ids.sort();
+
+    for id in ids {
+        let before = state.orders[&id].clone();
+        if rng.random_range(0..100) == 0 {
+            state.orders.remove(&id);
+            log.push("orders", "d", Some(Row::Order(before)), None);
+            continue;
+        }
+        let next_status = match before.status.as_str() {
+            "Pending" if before.date <= now => Some("Completed"),
+            "Backordered" if rng.random_range(0..4) == 0 => Some("Completed"),
+            "Completed" if state.refunds.get(&id).is_some_and(|due| *due <= now) => Some("Refunded"),
+            _ => None,
+        };
+        if let Some(status) = next_status {
+            let mut after = before.clone();
+            after.status = status.to_string();
+            if is_open(&after) { state.orders.insert(id.clone(), after.clone()); }
+            else { state.orders.remove(&id); }
+            log.push("orders", "u", Some(Row::Order(before)), Some(Row::Order(after)));
+        }
+    }
+}
+
+/// Insert the batch in order-date order, so customer changes reach the
+/// source table in the sequence they happened. Orders already refunded arrive
+/// as refunded; those with a refund still pending remember when it's due.
+fn insert_batch(state: &mut SourceState, log: &mut ChangeLog, orders: &[Order], returns: &[Return]) {
+    let mut pending: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    for ret in returns.iter().filter(|r| r.refunded_at.is_none()) {
+        pending.entry(&ret.order_id).and_modify(|due| *due = (*due).min(ret.refund_due)).or_insert(ret.refund_due);
+    }
+    let mut by_date: Vec<&Order> = orders.iter().collect();
+    by_date.sort_by_key(|o| o.date);
+    for order in by_date {
+        let customer = customer_row(order);
+        match state.customers.get(&customer.id) {
+            None => {
+                state.customers.insert(customer.id, customer.clone());
+                log.push("customers", "c", None, Some(Row::Customer(customer)));
+            }
+            Some(existing) if *existing != customer => {
+                let before = existing.clone();
+                state.customers.insert(customer.id, customer.clone());
+                log.push("customers", "u", Some(Row::Customer(before)), Some(Row::Customer(customer)));
+            }
+            Some(_) => {}
+        }
+        let row = order_row(order);
+        if is_open(&row) {
+            if let Some(due) = pending.remove(row.id.as_str()) {
+                state.refunds.insert(row.id.clone(), due.to_rfc3339());
+            }
+            state.orders.insert(row.id.clone(), row.clone());
+        }
+        log.push("orders", "c", None, Some(Row::Order(row)));
+    }
+}
+
+/// Keep at most `retain` open orders so the state file stays small
+fn trim_open_orders(state: &mut SourceState, retain: usize) {
+    if state.orders.len() <= retain {
+        return;
+    }
+    let mut rng = rand::rng();
+    let mut ids: Vec<String> = state.orders.keys().cloned().collect();
+    ids.sort();
+    while ids.len() > retain {
+        let index = rng.random_range(0..ids.len());
+        let id = ids.swap_remove(index);
+        state.orders.remove(&id);
+    }
+}
+
+fn is_open(row: &OrderRow) -> bool {
+    return row.status == "Pending" || row.status == "Completed" || row.status == "Backordered";
+}
+
+fn customer_row(order: &Order) -> CustomerRow {
+    return CustomerRow {
+        id: order.customer.id,
+        name: order.customer.name.clone(),
+        email: order.customer.email.clone(),
+        address: order.customer.address.clone(),
+        country: order.customer.country.clone(),
+        timezone: order.customer.timezone.clone(),
+        status: format!("{:?}", order.customer.status),
+    };
+}
+
+fn order_row(order: &Order) -> OrderRow {
+    return OrderRow {
+        id: order.id.clone(),
+        customer_id: order.customer.id,
+        product_id: order.product.id,
+        transaction_id: order.payment.transaction_id.clone(),
+        date: order.date.to_rfc3339(),
+        status: order.status.to_string(),
+        discount: order.discount,
+        quantity: order.quantity,
+        subtotal: order.subtotal,
+        discount_amount: order.discount_amount,
+        tax: order.tax,
+        shipping: order.shipping,
+        total: order.total,
+        currency: order.currency.clone(),
+        total_local: order.total_local,
+        cancel_reason: order.cancel_reason.clone(),
+    };
+}
+
+// ------------------------ Persistence ------------------------
+
+fn load_state(path: &Path) -> io::Result<SourceState> {
+    if !path.exists() {
+        return Ok(SourceState::default());
+    }
+    let reader = BufReader::new(File::open(path)?);
+    return serde_json::from_reader(reader).map_err(io::Error::other);
+}
+
+fn save_state(path: &Path, state: &SourceState) -> io::Result<()> {
+    return utils::write_atomic(path, |writer| serde_json::to_writer(writer, state).map_err(io::Error::other));
+}
+
+fn write_ndjson(events: &[ChangeEvent], path: &Path) -> io::Result<()> {
+    return utils::write_atomic(path, |writer| {
+        for event in events {
+            serde_json::to_writer(&mut *writer, event).map_err(io::Error::other)?;
+            writer.write_all(b"\n")?;
+        }
+        return Ok(());
+    });
+}
+
+/// Parquet change records keep the row images as JSON strings so both
+/// tables share one schema.
+fn write_events_parquet(events: &[ChangeEvent], path: &Path) -> io::Result<()> {
+    let to_json = |row: &Option<Row>| row.as_ref().map(serde_json::to_string).transpose();
+    let before: Vec<_> = events.iter().map(|e| to_json(&e.before)).collect::<Result<_, _>>().map_err(io::Error::other)?;
+    let after: Vec<_> = events.iter().map(|e| to_json(&e.after)).collect::<Result<_, _>>().map_err(io::Error::other)?;
+    let schema = Schema::from(vec![
+        Field::new("op", DataType::Utf8, false),
+        Field::new("before", DataType::Utf8, true),
+        Field::new("after", DataType::Utf8, true),
+        Field::new("table", DataType::Utf8, false),
+        Field::new("lsn", DataType::UInt64, false),
+        Field::new("batch", DataType::UInt64, false),
+        Field::new("ts_ms", DataType::Int64, false),
+    ]);
+    let chunk = Chunk::new(vec![
+        Arc::new(Utf8Array::<i32>::from_slice(events.iter().map(|e| e.op).collect::<Vec<_>>())) as Arc<dyn Array>,
+        Arc::new(Utf8Array::<i32>::from(before)),
+        Arc::new(Utf8Array::<i32>::from(after)),
+        Arc::new(Utf8Array::<i32>::from_slice(events.iter().map(|e| e.source.table).collect::<Vec<_>>())),
+        Arc::new(UInt64Array::from_slice(events.iter().map(|e| e.source.lsn).collect::<Vec<_>>())),
+        Arc::new(UInt64Array::from_slice(events.iter().map(|e| e.source.batch).collect::<Vec<_>>())),
+        Arc::new(Int64Array::from_slice(events.iter().map(|e| e.ts_ms).collect::<Vec<_>>())),
+    ]);
+    let path = path.to_str().unwrap_or_default();
+    return writer::write_chunk(schema, chunk, path).map_err(io::Error::other);
+}
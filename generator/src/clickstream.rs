@@ -0,0 +1,268 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::customers;
+use crate::orders::{Order, OrderContext, OrderStatus};
+use crate::products::{self, Product};
+use crate::timing;
+use crate::utils::generate_uuid;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Device {
+    Desktop,
+    Mobile,
+    Tablet,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Direct,
+    Search,
+    Email,
+    Social,
+    Ads,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    PageView,
+    ProductView,
+    AddToCart,
+    Checkout,
+    /// The order was placed; only the last event of a converting session
+    Purchase,
+    /// Payment was declined or the product was out of stock, and the order cancelled;
+    /// ends the session instead of `Purchase`
+    CheckoutFailed,
+}
+
+/// One visit to the store
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub session_id: String,
+    /// `None` for visitors who never signed in
+    pub customer_id: Option<i64>,
+    pub device: Device,
+    pub channel: Channel,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    /// The order placed at the end of the session, if it converted or failed at checkout
+    pub order_id: Option<String>,
+    pub event_count: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionEvent {
+    pub event_id: String,
+    pub session_id: String,
+    /// Position within the session, from 1
+    pub sequence: u32,
+    pub event_type: EventType,
+    pub page: String,
+    pub product_id: Option<i64>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct Clickstream {
+    pub sessions: Vec<Session>,
+    pub events: Vec<SessionEvent>,
+}
+
+/// Give every order the session it was placed in (setting `session_id`),
+/// then add browsing sessions that don't convert until `conversion_rate`
+/// of all sessions end in an order that went through. Each session draws from its own rng,
+/// seeded from `rng`.
+pub fn simulate(orders: &mut [Order], ctx: &OrderContext, conversion_rate: f64, rng: &mut impl Rng) -> Clickstream {
+    let (checkout_seed, browsing_seed): (u64, u64) = (rng.random(), rng.random());
+    let converting: Vec<(Session, Vec<SessionEvent>)> = {
+        orders
+            .par_iter_mut()
+            .enumerate()
+            .map(|(i, order)| checkout_session(order, &mut StdRng::seed_from_u64(checkout_seed.wrapping_add(i as u64))))
+            .collect()
+    };
+    // Sessions that failed at checkout didn't convert either
+    let converted = orders.iter().filter(|o| !matches!(o.status, OrderStatus::Cancelled)).count();
+    let failed = orders.len() - converted;
+    let browsing_count = {
+        ((converted as f64 * (1.0 - conversion_rate) / conversion_rate).round() as usize).saturating_sub(failed)
+    };
+    let browsing: Vec<(Session, Vec<SessionEvent>)> = {
+        (0..browsing_count)
+            .into_par_iter()
+            .map(|i| browsing_session(ctx, &mut StdRng::seed_from_u64(browsing_seed.wrapping_add(i as u64))))
+            .collect()
+    };
+
+    let mut clickstream = Clickstream::default();
+    for (session, events) in converting.into_iter().chain(browsing) {
+        clickstream.sessions.push(session);
+        clickstream.events.extend(events);
+    }
+    return clickstream;
+}
+
+/// The session an order was placed in: a landing page, some browsing, then
+/// the product, the cart and checkout, with the purchase at the order date.
+/// An order cancelled at checkout, for a declined payment or a stock-out,
+/// ends in a failed checkout instead.
+fn checkout_session(order: &mut Order, rng: &mut impl Rng) -> (Session, Vec<SessionEvent>) {
+    let mut trail = Trail::new(order.date, rng);
+    let channel = pick_channel(rng);
+    land(&mut trail, channel, &order.product, rng);
+    for _ in 0..rng.random_range(0..=4) {
+        let other = products::generate_product(rng);
+        trail.push(EventType::ProductView, product_page(&other), Some(other.id), rng);
+    }
+    trail.push(EventType::ProductView, product_page(&order.product), Some(order.product.id), rng);
+    trail.push(EventType::AddToCart, product_page(&order.product), Some(order.product.id), rng);
+    if rng.random_bool(0.5) {
+        trail.push(EventType::PageView, "/cart".to_string(), None, rng);
+    }
+    trail.push(EventType::Checkout, "/checkout".to_string(), None, rng);
+    match order.status {
+        OrderStatus::Cancelled => trail.push(EventType::CheckoutFailed, "/checkout".to_string(), None, rng),
+        _ => trail.push(EventType::Purchase, "/checkout/confirmation".to_string(), Some(order.product.id), rng),
+    }
+
+    // Shift the trail so the purchase (or failed checkout) lands on the order date
+    let offset = order.date - trail.at;
+    for event in &mut trail.events {
+        event.occurred_at += offset;
+    }
+    order.session_id = Some(trail.session_id.clone());
+    let device = {
+        if rng.random_bool(0.5) { Device::Desktop }
+        else if rng.random_bool(0.8) { Device::Mobile }
+        else { Device::Tablet }
+    };
+    return trail.finish(Some(order.customer.id), device, channel, Some(order.id.clone()));
+}
+
+/// A visit that leaves without ordering. Many bounce after the landing
+/// page; of those who shop, some abandon a cart, sometimes at checkout.
+fn browsing_session(ctx: &OrderContext, rng: &mut impl Rng) -> (Session, Vec<SessionEvent>) {
+    let customer = customers::generate_customer(ctx.customers, rng);
+    let started_at = ctx.time.sample(ctx.start, ctx.end, timing::utc_offset(&customer.timezone), rng);
+    let mut trail = Trail::new(started_at, rng);
+    let channel = pick_channel(rng);
+    let product = products::generate_product(rng);
+    land(&mut trail, channel, &product, rng);
+
+    if !rng.random_bool(BOUNCE_RATE) {
+        let mut viewed = product;
+        for _ in 0..rng.random_range(1..=6) {
+            viewed = products::generate_product(rng);
+            trail.push(EventType::ProductView, product_page(&viewed), Some(viewed.id), rng);
+        }
+        if rng.random_bool(CART_RATE) {
+            trail.push(EventType::AddToCart, product_page(&viewed), Some(viewed.id), rng);
+            if rng.random_bool(CHECKOUT_RATE) {
+                trail.push(EventType::Checkout, "/checkout".to_string(), None, rng);
+            }
+        }
+    }
+    let customer_id = if rng.random_bool(SIGNED_IN_SHARE) { Some(customer.id) } else { None };
+    let device = {
+        if rng.random_bool(0.35) { Device::Desktop }
+        else if rng.random_bool(0.85) { Device::Mobile }
+        else { Device::Tablet }
+    };
+    return trail.finish(customer_id, device, channel, None);
+}
+
+/// The first page of a session depends on how the visitor arrived
+fn land(trail: &mut Trail, channel: Channel, product: &Product, rng: &mut impl Rng) {
+    match channel {
+        Channel::Direct => trail.push(EventType::PageView, "/".to_string(), None, rng),
+        Channel::Search => {
+            let query = product.category.to_string().to_lowercase().replace(' ', "+");
+            trail.push(EventType::PageView, format!("/search?q={query}"), None, rng);
+        }
+        Channel::Email | Channel::Social => trail.push(EventType::PageView, category_page(product), None, rng),
+        Channel::Ads => trail.push(EventType::ProductView, product_page(product), Some(product.id), rng),
+    }
+}
+
+fn pick_channel(rng: &mut impl Rng) -> Channel {
+    return match rng.random_range(0..100) {
+        0..=29 => Channel::Direct,
+        30..=59 => Channel::Search,
+        60..=69 => Channel::Email,
+        70..=84 => Channel::Social,
+        _ => Channel::Ads,
+    };
+}
+
+fn product_page(product: &Product) -> String {
+    return format!("/product/{}", product.id);
+}
+
+fn category_page(product: &Product) -> String {
+    return format!("/category/{}", product.category.to_string().to_lowercase().replace(' ', "-"));
+}
+
+/// Events of a session being built, oldest first
+struct Trail {
+    session_id: String,
+    /// Time of the latest event
+    at: DateTime<Utc>,
+    events: Vec<SessionEvent>,
+}
+
+impl Trail {
+    fn new(start: DateTime<Utc>, rng: &mut impl Rng) -> Trail {
+        return Trail { session_id: generate_uuid(rng), at: start, events: Vec::new() };
+    }
+
+    /// Add an event a few seconds to a few minutes after the last one
+    fn push(&mut self, event_type: EventType, page: String, product_id: Option<i64>, rng: &mut impl Rng) {
+        if !self.events.is_empty() {
+            self.at += Duration::seconds(rng.random_range(5..180));
+        }
+        self.events.push(SessionEvent {
+            event_id: generate_uuid(rng),
+            session_id: self.session_id.clone(),
+            sequence: self.events.len() as u32 + 1,
+            event_type,
+            page,
+            product_id,
+            occurred_at: self.at,
+        });
+    }
+
+    fn finish(
+        self,
+        customer_id: Option<i64>,
+        device: Device,
+        channel: Channel,
+        order_id: Option<String>,
+    ) -> (Session, Vec<SessionEvent>) {
+        let session = Session {
+            session_id: self.session_id,
+            customer_id,
+            device,
+            channel,
+            started_at: self.events[0].occurred_at,
+            ended_at: self.events[self.events.len() - 1].occurred_at,
+            order_id,
+            event_count: self.events.len() as u32,
+        };
+        return (session, self.events);
+    }
+}
+
+// ------------------------ Static Data ------------------------
+
+/// Share of browsing sessions that leave after the landing page
+const BOUNCE_RATE: f64 = 0.45;
+/// Share of browsing shoppers who add something to their cart
+const CART_RATE: f64 = 0.3;
+/// Share of abandoned carts that get as far as checkout
+const CHECKOUT_RATE: f64 = 0.35;
+/// Share of browsing visitors signed in to an account
+const SIGNED_IN_SHARE: f64 = 0.6;
@@ -0,0 +1,291 @@
+use std::env;
+use std::process;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+
+use crate::cdc::CdcFormat;
+use crate::customers::DEFAULT_REWARDS_THRESHOLD;
+use crate::discounts::DiscountPolicy;
+use crate::error::{Error, Result};
+use crate::products::PRODUCT_COUNT;
+use crate::spec::Spec;
+use crate::timing::TimeModel;
+use crate::utils;
+
+#[derive(Serialize)]
+pub struct Config {
+    pub num_orders: usize,
+    pub output_dir: String,
+    pub cdc: Option<CdcFormat>,
+    pub cdc_retain: usize,
+    pub scd: bool,
+    pub rewards_threshold: f64,
+    pub discounts: DiscountPolicy,
+    pub time_model: TimeModel,
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+    /// Share of orders replaced by planted fraud incidents
+    pub fraud_rate: f64,
+    pub inventory: bool,
+    /// Share of clickstream sessions that end in an order; `None` writes no clickstream
+    pub conversion_rate: Option<f64>,
+    /// Write separate customer, product, payment and order files instead of data.parquet
+    pub star: bool,
+    /// Share of `--star` orders pointing at a customer or product missing from the dimensions
+    pub orphan_rate: f64,
+    /// Share of a `--star` batch's customers and products held back until the next batch
+    pub late_dimension_rate: f64,
+    /// Generate the tables of this spec instead of the order model
+    pub spec: Option<Spec>,
+    /// Seeds the spec generator or the order model; `None` draws a random seed
+    pub seed: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        return Config {
+            num_orders: 2_000_000,
+            output_dir: "../processor".to_string(),
+            cdc: None,
+            cdc_retain: 10_000,
+            scd: false,
+            rewards_threshold: DEFAULT_REWARDS_THRESHOLD,
+            discounts: DiscountPolicy::default(),
+            time_model: TimeModel::default(),
+            start: None,
+            end: None,
+            fraud_rate: 0.0,
+            inventory: false,
+            conversion_rate: None,
+            star: false,
+            orphan_rate: 0.0,
+            late_dimension_rate: 0.0,
+            spec: None,
+            seed: None,
+        };
+    }
+}
+
+impl Config {
+    /// The simulated period: `--start` through `--end` (inclusive), each
+    /// defaulting to the edges of `utils::simulation_window`
+    pub fn window(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let (default_start, default_end) = utils::simulation_window();
+        let start = match self.start {
+            Some(date) => date.and_time(NaiveTime::MIN).and_utc(),
+            None => default_start,
+        };
+        let end = match self.end {
+            Some(date) => date.and_time(NaiveTime::MIN).and_utc() + Duration::days(1),
+            None => default_end,
+        };
+        return (start, end);
+    }
+
+    /// Check the settings that only make sense together, or that a file
+    /// or builder could have set out of range
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.fraud_rate) {
+            return Err(invalid(&format!("The fraud rate must be between 0 and 1: {}", self.fraud_rate)));
+        }
+        if (self.orphan_rate > 0.0 || self.late_dimension_rate > 0.0) && !self.star {
+            return Err(invalid("--orphans and --late-dimensions need --star"));
+        }
+        self.discounts.check().map_err(|msg| invalid(&msg))?;
+        let (start, end) = self.window();
+        if start >= end {
+            return Err(invalid(&format!("The simulated period is empty: {} to {}", start.date_naive(), end.date_naive())));
+        }
+        let time_model = &self.time_model;
+        let weights = [
+            ("weekday", &time_model.weekday_weights[..]),
+            ("month", &time_model.month_weights[..]),
+            ("hour", &time_model.hour_weights[..]),
+        ];
+        for (name, weights) in weights {
+            if weights.iter().any(|weight| !weight.is_finite() || *weight < 0.0) {
+                return Err(invalid(&format!("The time model's {name} weights must be finite and not negative")));
+            }
+            if !weights.iter().any(|weight| *weight > 0.0) {
+                return Err(invalid(&format!("The time model needs at least one positive {name} weight")));
+            }
+        }
+        // Growth of -100% or less leaves no orders to sample after the start
+        let growth = time_model.annual_growth;
+        if !growth.is_finite() || growth <= -1.0 {
+            return Err(invalid(&format!("Annual growth must be finite and above -1: {growth}")));
+        }
+        for holiday in &self.time_model.holidays {
+            if !holiday.boost.is_finite() || holiday.boost < 0.0 {
+                return Err(invalid(&format!("Holiday '{}' needs a finite, non-negative boost", holiday.name)));
+            }
+            if let Some(id) = holiday.product_id
+                && !(0..PRODUCT_COUNT as i64).contains(&id)
+            {
+                return Err(invalid(&format!("Holiday '{}' names an unknown product: {id}", holiday.name)));
+            }
+        }
+        return Ok(());
+    }
+}
+
+pub const USAGE: &str = "\
+Usage: generator [NUM_ORDERS] [OPTIONS]
+
+Options:
+  --cdc <ndjson|parquet>     Also emit change-data-capture records for orders and customers
+  --cdc-retain <N>           Max open orders kept in the CDC source state (default 10000)
+  --scd                      Let customers move and change email over time,
+                             writing every version to customer_history.parquet
+  --rewards-threshold <USD>  Spend within a batch after which a customer becomes a rewards
+                             member (default 1000); status starts over with every batch
+  --discounts <FILE>         Load the discount rules from a JSON policy file
+  --time-model <FILE>        Load weekday/month/hour weights and holidays from a JSON file
+  --start <YYYY-MM-DD>       First day of the simulated period (default Jan 1 of this year)
+  --end <YYYY-MM-DD>         Last day of the simulated period (default a week from today)
+  --growth <RATE>            Year-over-year growth in order volume (default 0.1)
+  --fraud <RATE>             Share of orders planted as labeled fraud incidents,
+                             written to fraud_labels.parquet (default 0)
+  --inventory                Track stock per product; orders placed during stock-outs are
+                             backordered or cancelled, and daily stock levels are written
+                             to inventory_snapshots.parquet
+  --sessions <RATE>          Write browsing sessions and their events, where RATE of the
+                             sessions end in an order (e.g. 0.03), to sessions.parquet
+                             and session_events.parquet
+  --star                     Write customers, products, payments and orders as separate
+                             files keyed by id, instead of a single data.parquet
+  --orphans <RATE>           With --star, point RATE of the orders at a customer or product
+                             missing from the dimension files (default 0)
+  --late-dimensions <RATE>   With --star, hold back RATE of the batch's customers and products
+                             until the next batch (default 0)
+  --spec <FILE|default>      Generate the tables described by a TOML spec file instead of
+                             the order model, one <table>.parquet each; `default` is the
+                             built-in spec (default_spec.toml). Tables without a row count
+                             get NUM_ORDERS rows
+  --seed <N>                 Seed the orders and entities, or the spec tables (default a random
+                             seed, recorded in the manifest); with --spec, the same seed and
+                             period generate the same tables on every run
+  --help                     Show this message
+
+A successful run also writes manifest.json, listing each file written with its
+row count, size, SHA-256 checksum and date range, plus the seed and config.
+
+Exit codes: 0 success, 2 bad arguments or config files, 3 generation failed,
+4 file read/write failed, 5 Parquet encoding failed";
+
+/// Parse the command line. `--help` prints the usage and exits.
+pub fn parse_args() -> Result<Config> {
+    let mut config = Config::default();
+    let mut args = env::args().skip(1);
+    let mut seen_count = false;
+    let mut growth = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cdc" => {
+                config.cdc = Some(match next_value(&mut args, "--cdc")?.as_str() {
+                    "ndjson" => CdcFormat::Ndjson,
+                    "parquet" => CdcFormat::Parquet,
+                    other => return Err(invalid(&format!("Unknown CDC format: {other}"))),
+                });
+            }
+            "--cdc-retain" => {
+                config.cdc_retain = parse_number(&next_value(&mut args, "--cdc-retain")?, "--cdc-retain")?;
+            }
+            "--scd" => config.scd = true,
+            "--inventory" => config.inventory = true,
+            "--star" => config.star = true,
+            "--rewards-threshold" => {
+                config.rewards_threshold = parse_float(&next_value(&mut args, "--rewards-threshold")?, "--rewards-threshold")?;
+            }
+            "--discounts" => {
+                let path = next_value(&mut args, "--discounts")?;
+                config.discounts = DiscountPolicy::load(&path).map_err(|err| {
+                    invalid(&format!("Failed to load discount policy '{path}': {err}"))
+                })?;
+            }
+            "--time-model" => {
+                let path = next_value(&mut args, "--time-model")?;
+                config.time_model = TimeModel::load(&path).map_err(|err| {
+                    invalid(&format!("Failed to load time model '{path}': {err}"))
+                })?;
+            }
+            "--spec" => {
+                let path = next_value(&mut args, "--spec")?;
+                let spec = if path == "default" { Ok(Spec::builtin()) } else { Spec::load(&path) };
+                config.spec = Some(spec.map_err(|err| invalid(&format!("Failed to load spec '{path}': {err}")))?);
+            }
+            "--seed" => {
+                let value = next_value(&mut args, "--seed")?;
+                config.seed = Some(value.parse().map_err(|_| invalid(&format!("Invalid seed passed for --seed: {value}")))?);
+            }
+            "--start" => config.start = Some(parse_date(&next_value(&mut args, "--start")?, "--start")?),
+            "--end" => config.end = Some(parse_date(&next_value(&mut args, "--end")?, "--end")?),
+            "--growth" => growth = Some(parse_float(&next_value(&mut args, "--growth")?, "--growth")?),
+            "--fraud" => config.fraud_rate = parse_rate(&next_value(&mut args, "--fraud")?, "--fraud")?,
+            "--sessions" => {
+                let value = next_value(&mut args, "--sessions")?;
+                let rate = parse_float(&value, "--sessions")?;
+                if rate <= 0.0 || rate > 1.0 {
+                    return Err(invalid(&format!("--sessions must be above 0 and at most 1: {value}")));
+                }
+                config.conversion_rate = Some(rate);
+            }
+            "--orphans" => config.orphan_rate = parse_rate(&next_value(&mut args, "--orphans")?, "--orphans")?,
+            "--late-dimensions" => {
+                let value = next_value(&mut args, "--late-dimensions")?;
+                config.late_dimension_rate = parse_rate(&value, "--late-dimensions")?;
+            }
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+            _ if !seen_count && !arg.starts_with("--") => {
+                config.num_orders = arg.parse().map_err(|_| {
+                    invalid("Non-numeric argument passed for number of orders.")
+                })?;
+                seen_count = true;
+            }
+            _ => return Err(invalid(&format!("Unrecognized argument: {arg}"))),
+        }
+    }
+    // Applied last so it wins over a loaded --time-model
+    if let Some(growth) = growth {
+        config.time_model.annual_growth = growth;
+    }
+    config.validate()?;
+    return Ok(config);
+}
+
+// ------------------------ Helpers ------------------------
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String> {
+    return args.next().ok_or_else(|| invalid(&format!("Missing value for {flag}")));
+}
+
+fn parse_number(value: &str, flag: &str) -> Result<usize> {
+    return value.parse().map_err(|_| invalid(&format!("Non-numeric value passed for {flag}: {value}")));
+}
+
+fn parse_float(value: &str, flag: &str) -> Result<f64> {
+    return value.parse().map_err(|_| invalid(&format!("Non-numeric value passed for {flag}: {value}")));
+}
+
+fn parse_rate(value: &str, flag: &str) -> Result<f64> {
+    let rate = parse_float(value, flag)?;
+    if !(0.0..=1.0).contains(&rate) {
+        return Err(invalid(&format!("{flag} must be between 0 and 1: {value}")));
+    }
+    return Ok(rate);
+}
+
+fn parse_date(value: &str, flag: &str) -> Result<NaiveDate> {
+    return NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        invalid(&format!("Invalid date passed for {flag}: {value} (expected YYYY-MM-DD)"))
+    });
+}
+
+fn invalid(msg: &str) -> Error {
+    return Error::Config(msg.to_string());
+}
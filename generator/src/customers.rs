@@ -1,8 +1,12 @@
-use rand::Rng;
-use serde::Serialize;
+use chrono::{DateTime, Duration, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Clone, Copy)]
-#[allow(dead_code)]
+use crate::error::{Error, Result};
+use crate::timing::timezone_for;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum CustomerStatus {
     NewCustomer,
     ReturningCustomer,
@@ -12,38 +16,286 @@ pub enum CustomerStatus {
     Owner,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Customer {
     pub id: i64,
     pub name: String,
     pub email: String,
     pub address: String,
+    /// ISO 3166 alpha-2 country code
+    pub country: String,
+    /// IANA time zone, following the address
+    pub timezone: String,
     pub status: CustomerStatus,
 }
 
-pub fn generate_customer() -> Customer {
-    let name = get_name();
-    return Customer {
-        id: get_customer_id(&name),
-        name: name.clone(),
-        email: get_email(&name),
-        address: get_address(&name),
-        status: get_status(&name),
+/// A random customer from `customers`, as returned by `all_customers`
+pub fn generate_customer(customers: &[Customer], rng: &mut impl Rng) -> Customer {
+    return customers[get_index(customers.len(), rng)].clone();
+}
+
+/// Every customer as they start out. Fails if the customer tables are
+/// missing someone's contact details.
+pub fn all_customers() -> Result<Vec<Customer>> {
+    return NAMES.iter().map(|name| customer_by_name(name)).collect();
+}
+
+fn customer_by_name(name: &str) -> Result<Customer> {
+    let country = get_country(name);
+    let address = get_address(name)?;
+    return Ok(Customer {
+        id: get_customer_id(name),
+        name: name.to_string(),
+        email: get_email(name)?,
+        timezone: timezone_for(country, &address).to_string(),
+        address,
+        country: country.to_string(),
+        status: get_role(name).unwrap_or(CustomerStatus::NewCustomer),
+    });
+}
+
+// ------------------------ Slowly Changing Attributes ------------------------
+
+/// One SCD Type 2 version of a customer, valid on `[effective_from, effective_to)`
+#[derive(Debug, Clone)]
+pub struct CustomerVersion {
+    pub customer: Customer,
+    pub version: u32,
+    pub effective_from: DateTime<Utc>,
+    pub effective_to: Option<DateTime<Utc>>,
+}
+
+/// Every version of every customer over the simulated period
+pub struct CustomerHistory {
+    versions: Vec<Vec<CustomerVersion>>,
+}
+
+impl CustomerHistory {
+    /// Simulate moves, email changes and promotions between `start` and `end`.
+    /// Each customer's timeline is seeded by their id, so separate batches
+    /// covering the same period agree on the history.
+    pub fn simulate(customers: &[Customer], start: DateTime<Utc>, end: DateTime<Utc>) -> CustomerHistory {
+        let versions = customers.iter().map(|customer| simulate_timeline(customer, start, end)).collect();
+        return CustomerHistory { versions };
+    }
+
+    /// `customer` as of `date`, or as given if they have no history
+    pub fn customer_at<'a>(&'a self, customer: &'a Customer, date: DateTime<Utc>) -> &'a Customer {
+        let Some(timeline) = self.versions.iter().find(|t| t[0].customer.id == customer.id) else {
+            return customer;
+        };
+        let version = {
+            timeline
+                .iter()
+                .rev()
+                .find(|v| v.effective_from <= date)
+                .unwrap_or(&timeline[0])
+        };
+        return &version.customer;
+    }
+
+    pub fn versions(&self) -> impl Iterator<Item = &CustomerVersion> {
+        return self.versions.iter().flatten();
+    }
+
+    /// Split versions wherever a customer's derived status changed, so the
+    /// history records promotions alongside moves and email changes.
+    pub fn apply_status_changes(&mut self, changes: &[StatusChange]) {
+        for timeline in &mut self.versions {
+            let id = timeline[0].customer.id;
+            let changes: Vec<&StatusChange> = changes.iter().filter(|c| c.customer_id == id).collect();
+            if changes.is_empty() {
+                continue;
+            }
+            split_timeline(timeline, changes.iter().map(|c| c.date), |from, customer| {
+                if let Some(change) = changes.iter().rev().find(|c| c.date <= from) {
+                    customer.status = change.status;
+                }
+            });
+        }
+    }
+
+    /// Split versions around each override, replacing the customer's
+    /// contact details while it lasts
+    pub fn apply_contact_overrides(&mut self, overrides: &[ContactOverride]) {
+        for timeline in &mut self.versions {
+            let id = timeline[0].customer.id;
+            let overrides: Vec<&ContactOverride> = overrides.iter().filter(|o| o.customer_id == id).collect();
+            if overrides.is_empty() {
+                continue;
+            }
+            let dates = overrides.iter().flat_map(|o| std::iter::once(o.from).chain(o.to));
+            split_timeline(timeline, dates, |from, customer| {
+                if let Some(o) = overrides.iter().find(|o| o.from <= from && o.to.is_none_or(|to| from < to)) {
+                    customer.email = o.email.clone();
+                    customer.address = o.address.clone();
+                    customer.timezone = o.timezone.clone();
+                }
+            });
+        }
+    }
+}
+
+/// Someone else's contact details on a customer's account from `from`
+/// until `to`, or for the rest of the period
+#[derive(Debug, Clone)]
+pub struct ContactOverride {
+    pub customer_id: i64,
+    pub email: String,
+    pub address: String,
+    pub timezone: String,
+    pub from: DateTime<Utc>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Rebuild a timeline with extra version boundaries at `dates`, letting
+/// `adjust` edit the attributes of each version from its start date.
+fn split_timeline(
+    timeline: &mut Vec<CustomerVersion>,
+    dates: impl Iterator<Item = DateTime<Utc>>,
+    adjust: impl Fn(DateTime<Utc>, &mut Customer),
+) {
+    let first = timeline[0].effective_from;
+    let mut boundaries: Vec<DateTime<Utc>> = {
+        timeline
+            .iter()
+            .map(|v| v.effective_from)
+            .chain(dates.filter(|d| *d >= first))
+            .collect()
     };
+    boundaries.sort();
+    boundaries.dedup();
+
+    let merged = boundaries.iter().enumerate().map(|(i, from)| {
+        let attributes = timeline.iter().rev().find(|v| v.effective_from <= *from).unwrap_or(&timeline[0]);
+        let mut customer = attributes.customer.clone();
+        adjust(*from, &mut customer);
+        CustomerVersion {
+            customer,
+            version: i as u32 + 1,
+            effective_from: *from,
+            effective_to: boundaries.get(i + 1).copied(),
+        }
+    })
+    .collect();
+    *timeline = merged;
 }
 
-// ------------------------ Data + Logic ------------------------
+fn simulate_timeline(customer: &Customer, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<CustomerVersion> {
+    let mut rng = StdRng::seed_from_u64(SCD_SEED ^ customer.id as u64);
+    let mut current = customer.clone();
+    let mut timeline = vec![CustomerVersion {
+        customer: current.clone(),
+        version: 1,
+        effective_from: start,
+        effective_to: None,
+    }];
+    let mut date = start;
+    loop {
+        // Roughly one change every three months
+        date += Duration::hours(rng.random_range(24..24 * 180));
+        if date >= end {
+            break;
+        }
+        let mut next = current.clone();
+        match rng.random_range(0..10) {
+            0..=5 => move_house(&mut next, &mut rng),
+            _ if !next.email.ends_with("@store.com") => next.email = random_email(&customer.name, &mut rng),
+            _ => move_house(&mut next, &mut rng),
+        }
+        let last = timeline.last_mut().unwrap();
+        last.effective_to = Some(date);
+        let version = last.version + 1;
+        timeline.push(CustomerVersion {
+            customer: next.clone(),
+            version,
+            effective_from: date,
+            effective_to: None,
+        });
+        current = next;
+    }
+    return timeline;
+}
+
+fn move_house(customer: &mut Customer, rng: &mut StdRng) {
+    let address = random_address(&customer.country, rng);
+    customer.timezone = timezone_for(&customer.country, &address).to_string();
+    customer.address = address;
+}
+
+/// A new address in the same country; customers abroad move between the
+/// cities listed for their country.
+pub fn random_address(country: &str, rng: &mut impl Rng) -> String {
+    if country != "US" {
+        let options: Vec<&str> = {
+            INTERNATIONAL_ADDRESSES
+                .iter()
+                .filter(|(c, _)| *c == country)
+                .map(|(_, a)| *a)
+                .collect()
+        };
+        if !options.is_empty() {
+            let address = options[rng.random_range(0..options.len())];
+            return format!("{} {address}", rng.random_range(1..400));
+        }
+    }
+    let street = STREETS[rng.random_range(0..STREETS.len())];
+    let city = CITIES[rng.random_range(0..CITIES.len())];
+    return format!("{} {street}, {city}", rng.random_range(10..9999));
+}
+
+fn random_email(name: &str, rng: &mut StdRng) -> String {
+    let (first, last) = name.split_once(' ').unwrap_or((name, ""));
+    let (first, last) = (first.to_lowercase(), last.to_lowercase());
+    let provider = EMAIL_PROVIDERS[rng.random_range(0..EMAIL_PROVIDERS.len())];
+    return match rng.random_range(0..3) {
+        0 => format!("{first}.{last}@{provider}"),
+        1 => format!("{}{last}{}@{provider}", &first[..1], rng.random_range(1..100)),
+        _ => format!("{last}_{first}{}@{provider}", rng.random_range(1..1000)),
+    };
+}
+
+// ------------------------ Purchase History ------------------------
+
+/// Spend (after discounts) that turns a returning customer into a rewards member
+pub const DEFAULT_REWARDS_THRESHOLD: f64 = 1_000.0;
+
+/// A customer's status changing as of `date`
+#[derive(Debug, Clone)]
+pub struct StatusChange {
+    pub customer_id: i64,
+    pub date: DateTime<Utc>,
+    pub status: CustomerStatus,
+}
+
+/// Running purchase totals for one customer
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PurchaseHistory {
+    pub orders: u32,
+    pub spend: f64,
+}
 
-fn get_name() -> String {
-    let names = NAMES;
-    let mut rng = rand::rng();
+/// Employees, managers and the owner keep their role; everyone else is
+/// a new customer until their first order, then returning, then a rewards
+/// member once they have spent `rewards_threshold`.
+pub fn status_from_history(customer: &Customer, history: &PurchaseHistory, rewards_threshold: f64) -> CustomerStatus {
+    if let Some(role) = get_role(&customer.name) {
+        return role;
+    }
+    if history.orders == 0 { CustomerStatus::NewCustomer }
+    else if history.spend < rewards_threshold { CustomerStatus::ReturningCustomer }
+    else { CustomerStatus::RewardsMember }
+}
 
-    let mut choice: usize = rng.random_range(0..25);
+// ------------------------ Data + Logic ------------------------
+
+fn get_index(count: usize, rng: &mut impl Rng) -> usize {
+    let mut choice: usize = rng.random_range(0..count);
     if rng.random_range(0..5) == 0 {
         // 20% chance to shift index
         choice = choice.saturating_sub(rng.random_range(0..10));
     }
-    return names[choice].to_string();
+    return choice;
 }
 
 fn get_customer_id(name: &str) -> i64 {
@@ -55,76 +307,96 @@ fn get_customer_id(name: &str) -> i64 {
     };
 }
 
-fn get_email(name: &str) -> String {
+fn get_email(name: &str) -> Result<String> {
     return {
         CUSTOMER_EMAILS
             .iter()
             .find(|(n, _)| *n == name)
-            .unwrap()
-            .1
-            .to_string()
+            .map(|(_, email)| email.to_string())
+            .ok_or_else(|| Error::Generation(format!("No email on file for customer {name}")))
     };
 }
 
-fn get_address(name: &str) -> String {
+fn get_country(name: &str) -> &'static str {
+    return {
+        CUSTOMER_COUNTRIES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, country)| *country)
+            .unwrap_or("US")
+    };
+}
+
+fn get_address(name: &str) -> Result<String> {
     return {
         CUSTOMER_ADDRESSES
             .iter()
             .find(|(n, _)| *n == name)
-            .unwrap()
-            .1
-            .to_string()
+            .map(|(_, address)| address.to_string())
+            .ok_or_else(|| Error::Generation(format!("No address on file for customer {name}")))
     };
 }
 
-fn get_status(name: &str) -> CustomerStatus {
+/// Roles are assigned, not earned
+fn get_role(name: &str) -> Option<CustomerStatus> {
     return match name {
-        "Maya Ellison" | "Noah Blackwood" | "Chloe Hartman" |
-        "Lucas Pennington" | "Sofia Langford" | "Oliver Drayton" |
-        "Harper Linwood" => {
-            CustomerStatus::ReturningCustomer
-        },
-        "Sebastian Knox" | "Amelia Fairbanks" | "Julian Royce" |
-        "Nora Halston" | "Elijah Trent" | "Zoe Merrick" => {
-            CustomerStatus::RewardsMember
-        },
         "Caleb Winslow" | "Lily Hargrove" | "Milo Carrington" |
         "Aria Templeton" | "Declan Shore" => {
-            CustomerStatus::Employee
+            Some(CustomerStatus::Employee)
         },
         "Vivian Leclair" | "Grayson Holt" => {
-            CustomerStatus::Manager
+            Some(CustomerStatus::Manager)
         },
         "Clara Redmond" => {
-            CustomerStatus::Owner
+            Some(CustomerStatus::Owner)
         },
-        _ =>CustomerStatus::NewCustomer,
+        _ => None,
     };
 }
 
 // ------------------------ Static Data ------------------------
 
-const NAMES: [&str; 25] = [
+const SCD_SEED: u64 = 0x5CD2_C057;
+
+const STREETS: [&str; 12] = [
+    "Cedar Point Dr", "Larkspur Ln", "Fox Run Rd", "Harbor View Ct", "Elm Terrace",
+    "Juniper Way", "Stonegate Blvd", "Orchard Hill Rd", "Willowbrook Cir", "Aspen Trl",
+    "Magnolia St", "Copper Canyon Dr",
+];
+
+const CITIES: [&str; 12] = [
+    "Denver, CO 80205", "Portland, OR 97214", "Nashville, TN 37206", "Columbus, OH 43215",
+    "Richmond, VA 23220", "Boise, ID 83702", "Savannah, GA 31401", "Omaha, NE 68102",
+    "Raleigh, NC 27601", "Tucson, AZ 85701", "Burlington, VT 05401", "Sacramento, CA 95814",
+];
+
+const EMAIL_PROVIDERS: [&str; 4] = [
+    "email.com", "emailprovider.com", "upmail.com", "downmail.com",
+];
+
+const NAMES: [&str; 32] = [
     "Ava Whitaker", "Liam Caldwell", "Isabella Greene", "Ethan Morrell",
     "Maya Ellison", "Noah Blackwood", "Chloe Hartman", "Lucas Pennington",
     "Sofia Langford", "Oliver Drayton", "Harper Linwood", "Sebastian Knox",
     "Amelia Fairbanks", "Julian Royce", "Nora Halston", "Elijah Trent",
     "Zoe Merrick", "Caleb Winslow", "Lily Hargrove", "Milo Carrington",
     "Aria Templeton", "Declan Shore", "Vivian Leclair", "Grayson Holt",
-    "Clara Redmond"
+    "Clara Redmond", "Ethan Tremblay", "Olivia Bennett", "Lukas Becker",
+    "Camille Laurent", "Haruto Sato", "Valentina Ruiz", "Jack Thompson",
 ];
 
-const CUSTOMER_IDS: [(&str, i64); 25] = [
+const CUSTOMER_IDS: [(&str, i64); 32] = [
     ("Ava Whitaker", 24), ("Liam Caldwell", 23), ("Isabella Greene", 22), ("Ethan Morrell", 21),
     ("Maya Ellison", 20), ("Noah Blackwood", 19), ("Chloe Hartman", 18), ("Lucas Pennington", 17),
     ("Sofia Langford", 16), ("Oliver Drayton", 15), ("Harper Linwood", 14), ("Sebastian Knox", 13),
     ("Amelia Fairbanks", 12), ("Julian Royce", 11), ("Nora Halston", 10), ("Elijah Trent", 9),
     ("Zoe Merrick", 8), ("Caleb Winslow", 7), ("Lily Hargrove", 6), ("Milo Carrington", 5),
     ("Aria Templeton", 4), ("Declan Shore", 3), ("Vivian Leclair", 2), ("Grayson Holt", 1),
-    ("Clara Redmond", 0),
+    ("Clara Redmond", 0), ("Ethan Tremblay", 25), ("Olivia Bennett", 26), ("Lukas Becker", 27),
+    ("Camille Laurent", 28), ("Haruto Sato", 29), ("Valentina Ruiz", 30), ("Jack Thompson", 31),
 ];
 
-const CUSTOMER_EMAILS: [(&str, &str); 25] = [
+const CUSTOMER_EMAILS: [(&str, &str); 32] = [
     ("Ava Whitaker", "whitaker.a@email.com"),
     ("Liam Caldwell", "caldwelll@email.com"),
     ("Isabella Greene", "isabella_greene@email.com"),
@@ -150,9 +422,16 @@ const CUSTOMER_EMAILS: [(&str, &str); 25] = [
     ("Vivian Leclair", "manager1@store.com"),
     ("Grayson Holt", "manager2@store.com"),
     ("Clara Redmond", "owner@store.com"),
+    ("Ethan Tremblay", "e.tremblay@email.ca"),
+    ("Olivia Bennett", "olivia.bennett@upmail.co.uk"),
+    ("Lukas Becker", "lukas.becker@email.de"),
+    ("Camille Laurent", "camille_laurent@emailprovider.fr"),
+    ("Haruto Sato", "sato.haruto@email.jp"),
+    ("Valentina Ruiz", "vale.ruiz88@email.mx"),
+    ("Jack Thompson", "jackt@upmail.com.au"),
 ];
 
-const CUSTOMER_ADDRESSES: [(&str, &str); 25] = [
+const CUSTOMER_ADDRESSES: [(&str, &str); 32] = [
     ("Ava Whitaker", "1281 Marigold Ln, Boulder, CO 80304"),
     ("Liam Caldwell", "760 Pine Hollow Rd, Albany, NY 12205"),
     ("Isabella Greene", "3947 Sycamore Dr, San Diego, CA 92103"),
@@ -178,5 +457,36 @@ const CUSTOMER_ADDRESSES: [(&str, &str); 25] = [
     ("Vivian Leclair", "6307 Oakdale Way, Apex, NC 27502"),
     ("Grayson Holt", "214 Forest Glen Ct, Garner, NC 27529"),
     ("Clara Redmond", "1201 Amberwood Dr, Holly Springs, NC 27540"),
+    ("Ethan Tremblay", "88 Queen St W, Toronto, ON M5H 2N2, Canada"),
+    ("Olivia Bennett", "14 Kensington Rd, London W8 4PT, United Kingdom"),
+    ("Lukas Becker", "112 Friedrichstraße, 10117 Berlin, Germany"),
+    ("Camille Laurent", "27 Rue de Rivoli, 75004 Paris, France"),
+    ("Haruto Sato", "2-11-3 Meguro, Meguro-ku, Tokyo 153-0063, Japan"),
+    ("Valentina Ruiz", "222 Av. Reforma, Juárez, 06600 Ciudad de México, Mexico"),
+    ("Jack Thompson", "45 George St, Sydney NSW 2000, Australia"),
+];
+
+/// Customers living outside the US; everyone else is in the US
+const CUSTOMER_COUNTRIES: [(&str, &str); 7] = [
+    ("Ethan Tremblay", "CA"), ("Olivia Bennett", "GB"), ("Lukas Becker", "DE"),
+    ("Camille Laurent", "FR"), ("Haruto Sato", "JP"), ("Valentina Ruiz", "MX"),
+    ("Jack Thompson", "AU"),
+];
+
+const INTERNATIONAL_ADDRESSES: [(&str, &str); 14] = [
+    ("CA", "Robson St, Vancouver, BC V6B 2A3, Canada"),
+    ("CA", "Rue Sainte-Catherine, Montréal, QC H3B 1A7, Canada"),
+    ("GB", "Deansgate, Manchester M3 2BW, United Kingdom"),
+    ("GB", "Princes St, Edinburgh EH2 2AN, United Kingdom"),
+    ("DE", "Leopoldstraße, 80802 München, Germany"),
+    ("DE", "Mönckebergstraße, 20095 Hamburg, Germany"),
+    ("FR", "Rue de la République, 69002 Lyon, France"),
+    ("FR", "Cours Mirabeau, 13100 Aix-en-Provence, France"),
+    ("JP", "Shijō-dōri, Shimogyō-ku, Kyoto 600-8001, Japan"),
+    ("JP", "Midosuji, Chūō-ku, Osaka 542-0081, Japan"),
+    ("MX", "Av. Chapultepec, Guadalajara, 44100 Jalisco, Mexico"),
+    ("MX", "Calle 60, Centro, 97000 Mérida, Mexico"),
+    ("AU", "Collins St, Melbourne VIC 3000, Australia"),
+    ("AU", "Queen St, Brisbane City QLD 4000, Australia"),
 ];
 
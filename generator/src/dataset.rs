@@ -0,0 +1,228 @@
+use chrono::NaiveDate;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::clickstream::{self, Clickstream};
+use crate::config::Config;
+use crate::customers::{self, CustomerHistory};
+use crate::error::Result;
+use crate::discounts::DiscountPolicy;
+use crate::fraud::{self, FraudLabel};
+use crate::fx::FxTable;
+use crate::inventory::{self, InventorySnapshot};
+use crate::orders::{self, Order, OrderContext, OrderStatus};
+use crate::payments::InstrumentBook;
+use crate::registry::{GenContext, Generate, Registry, Table, Tabular};
+use crate::returns::{self, Return};
+use crate::reviews::{self, Review};
+use crate::shipments::{self, Shipment};
+use crate::timing::TimeModel;
+
+/// Everything one run generates, ready for a `Sink`
+pub struct Dataset {
+    pub orders: Vec<Order>,
+    pub shipments: Vec<Shipment>,
+    pub returns: Vec<Return>,
+    pub reviews: Vec<Review>,
+    /// `None` unless sessions were enabled
+    pub clickstream: Option<Clickstream>,
+    /// `None` unless fraud was planted
+    pub fraud_labels: Option<Vec<FraudLabel>>,
+    pub fraud_incidents: usize,
+    /// `None` unless inventory was tracked
+    pub snapshots: Option<Vec<InventorySnapshot>>,
+    /// Only with slowly changing customers
+    pub history: Option<CustomerHistory>,
+    pub fx: FxTable,
+    pub instruments: InstrumentBook,
+    /// Entity tables registered with `OrderGenerator::register`
+    pub tables: Vec<Table>,
+    /// What the orders, entities and fraud were drawn from
+    pub seed: u64,
+}
+
+/// Builds a `Dataset` in-process. Starts from the command line defaults:
+///
+/// ```no_run
+/// use generator::OrderGenerator;
+///
+/// let dataset = OrderGenerator::new(10_000).fraud_rate(0.01).inventory(true).generate()?;
+/// # Ok::<(), generator::Error>(())
+/// ```
+pub struct OrderGenerator {
+    config: Config,
+    registry: Registry,
+}
+
+impl OrderGenerator {
+    pub fn new(num_orders: usize) -> OrderGenerator {
+        return OrderGenerator::from_config(Config { num_orders, ..Config::default() });
+    }
+
+    /// Use the generation settings of a parsed command line
+    pub fn from_config(config: Config) -> OrderGenerator {
+        return OrderGenerator { config, registry: Registry::default() };
+    }
+
+    /// Simulate `start` through `end`, both inclusive
+    pub fn period(mut self, start: NaiveDate, end: NaiveDate) -> Self {
+        self.config.start = Some(start);
+        self.config.end = Some(end);
+        return self;
+    }
+
+    pub fn scd(mut self, enabled: bool) -> Self {
+        self.config.scd = enabled;
+        return self;
+    }
+
+    pub fn rewards_threshold(mut self, usd: f64) -> Self {
+        self.config.rewards_threshold = usd;
+        return self;
+    }
+
+    pub fn discounts(mut self, policy: DiscountPolicy) -> Self {
+        self.config.discounts = policy;
+        return self;
+    }
+
+    pub fn time_model(mut self, model: TimeModel) -> Self {
+        self.config.time_model = model;
+        return self;
+    }
+
+    pub fn fraud_rate(mut self, rate: f64) -> Self {
+        self.config.fraud_rate = rate;
+        return self;
+    }
+
+    pub fn inventory(mut self, enabled: bool) -> Self {
+        self.config.inventory = enabled;
+        return self;
+    }
+
+    /// Generate clickstream sessions, `conversion_rate` of which end in an order
+    pub fn sessions(mut self, conversion_rate: f64) -> Self {
+        self.config.conversion_rate = Some(conversion_rate);
+        return self;
+    }
+
+    /// Seed the orders, registered entities and planted fraud; without one a random seed is drawn
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        return self;
+    }
+
+    /// Also generate `count` rows of `T`, written as a table called `name`
+    pub fn register<T: Generate + Tabular + 'static>(mut self, name: &str, count: usize) -> Self {
+        self.registry.register::<T>(name, count);
+        return self;
+    }
+
+    pub fn config(&self) -> &Config {
+        return &self.config;
+    }
+
+    /// Fails if the settings don't hold together (see `Config::validate`)
+    pub fn generate(&self) -> Result<Dataset> {
+        let config = &self.config;
+        config.validate()?;
+        let num_orders = config.num_orders;
+        let now = chrono::Utc::now();
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        // ----------------------- Simulate customer history, FX and payment instruments -----------------------
+        let (start, end) = config.window();
+        let customers = customers::all_customers()?;
+        let mut history = if config.scd {
+            Some(CustomerHistory::simulate(&customers, start, end))
+        }
+        else { None };
+        let fx = FxTable::simulate(start, end);
+        let instruments = InstrumentBook::simulate(&customers, end);
+
+        // ----------------------- Generate in parallel -----------------------
+        let ctx = OrderContext {
+            customers: &customers,
+            history: history.as_ref(),
+            discounts: &config.discounts,
+            rewards_threshold: config.rewards_threshold,
+            fx: &fx,
+            instruments: &instruments,
+            time: &config.time_model,
+            start,
+            end,
+        };
+        let fraud_orders = (num_orders as f64 * config.fraud_rate).round() as usize;
+        let order_seed: u64 = rng.random();
+        let mut orders: Vec<Order> = {
+            (0..num_orders - fraud_orders)
+                .into_par_iter()
+                .map(|i| Order::generate(&mut GenContext::new(config, &ctx, now, order_seed.wrapping_add(i as u64))))
+                .collect()
+        };
+
+        // ----------------------- Generate registered entities -----------------------
+        let tables = self.registry.build(&mut GenContext::new(config, &ctx, now, rng.random()));
+
+        // ----------------------- Plant fraud -----------------------
+        let fraud = fraud::plant_incidents(&ctx, fraud_orders, now, &mut rng);
+        orders.extend(fraud.orders);
+
+        // ----------------------- Replay purchase history -----------------------
+        let status_changes = orders::apply_purchase_history(&mut orders, &ctx, now, &mut rng);
+
+        // ----------------------- Fill from stock -----------------------
+        let snapshots = if config.inventory {
+            Some(inventory::apply_inventory(&mut orders, start, end))
+        }
+        else { None };
+
+        // ----------------------- Simulate browsing sessions -----------------------
+        // After inventory, so orders cancelled for a stock-out end in a failed checkout
+        let clickstream = config.conversion_rate.map(|rate| clickstream::simulate(&mut orders, &ctx, rate, &mut rng));
+
+        // ----------------------- Record contact and status changes -----------------------
+        if let Some(history) = &mut history {
+            history.apply_contact_overrides(&fraud.overrides);
+            history.apply_status_changes(&status_changes);
+        }
+
+        // ----------------------- Fulfill, take returns and collect reviews -----------------------
+        let fulfil_seed: u64 = rng.random();
+        let (shipments, (returns, reviews)): (Vec<_>, (Vec<_>, Vec<_>)) = {
+            orders
+                .par_iter_mut()
+                .enumerate()
+                .map(|(i, order)| {
+                    let mut rng = StdRng::seed_from_u64(fulfil_seed.wrapping_add(i as u64));
+                    let shipments = shipments::plan_shipments(order, &mut rng, now);
+                    let returns = returns::plan_returns(order, &shipments, &mut rng, now);
+                    let review = reviews::plan_review(order, &shipments, &returns, &mut rng, now);
+                    if returns.iter().any(|r| r.refunded_at.is_some()) {
+                        order.status = OrderStatus::Refunded;
+                    }
+                    (shipments, (returns, review))
+                })
+                .unzip()
+        };
+
+        return Ok(Dataset {
+            orders,
+            shipments: shipments.into_iter().flatten().collect(),
+            returns: returns.into_iter().flatten().collect(),
+            reviews: reviews.into_iter().flatten().collect(),
+            clickstream,
+            fraud_labels: if config.fraud_rate > 0.0 { Some(fraud.labels) } else { None },
+            fraud_incidents: fraud.incidents,
+            snapshots,
+            history,
+            fx,
+            instruments,
+            tables,
+            seed,
+        });
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::customers::{Customer, CustomerStatus};
+use crate::products::{Product, ProductCategory};
+
+/// Discount every customer with `status` gets on a `probability` share of orders
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusRate {
+    pub status: CustomerStatus,
+    pub rate: f64,
+    #[serde(default = "always")]
+    pub probability: f64,
+}
+
+/// A code redeemed on a `usage` share of orders placed inside its validity window
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromoCode {
+    pub code: String,
+    pub rate: f64,
+    pub valid_from: NaiveDate,
+    pub valid_to: NaiveDate,
+    pub usage: f64,
+}
+
+/// A sale on one product category inside a validity window
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CategoryPromotion {
+    pub name: String,
+    pub category: ProductCategory,
+    pub rate: f64,
+    pub valid_from: NaiveDate,
+    pub valid_to: NaiveDate,
+}
+
+/// The rules deciding each order's discount. Rules don't stack: the most
+/// generous one that applies wins, then `max_discount` caps it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiscountPolicy {
+    #[serde(default)]
+    pub status_rates: Vec<StatusRate>,
+    #[serde(default)]
+    pub promo_codes: Vec<PromoCode>,
+    #[serde(default)]
+    pub category_promotions: Vec<CategoryPromotion>,
+    #[serde(default = "always")]
+    pub max_discount: f64,
+}
+
+/// The discount an order gets and the rule that granted it
+#[derive(Debug, Clone)]
+pub struct AppliedDiscount {
+    pub rate: f64,
+    pub reason: String,
+}
+
+impl Default for DiscountPolicy {
+    /// The original hard-coded per-status rates
+    fn default() -> Self {
+        let rate = |status, rate, probability| StatusRate { status, rate, probability };
+        return DiscountPolicy {
+            status_rates: vec![
+                rate(CustomerStatus::NewCustomer, 0.05, 1.0),
+                rate(CustomerStatus::ReturningCustomer, 0.03, 0.5),
+                rate(CustomerStatus::RewardsMember, 0.10, 1.0),
+                rate(CustomerStatus::Employee, 0.20, 1.0),
+                rate(CustomerStatus::Manager, 0.50, 1.0),
+                rate(CustomerStatus::Owner, 1.00, 1.0),
+            ],
+            promo_codes: Vec::new(),
+            category_promotions: Vec::new(),
+            max_discount: 1.0,
+        };
+    }
+}
+
+impl DiscountPolicy {
+    /// Load and check a policy file
+    pub fn load(path: &str) -> io::Result<DiscountPolicy> {
+        let reader = BufReader::new(File::open(path)?);
+        let policy: DiscountPolicy = serde_json::from_reader(reader).map_err(io::Error::other)?;
+        policy.check().map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))?;
+        return Ok(policy);
+    }
+
+    /// Every rate, share and the cap must be a fraction, or totals could go negative
+    pub fn check(&self) -> Result<(), String> {
+        let fraction = |name: String, value: f64| {
+            if (0.0..=1.0).contains(&value) { Ok(()) }
+            else { Err(format!("The discount {name} must be between 0 and 1: {value}")) }
+        };
+        for rule in &self.status_rates {
+            fraction(format!("status:{:?} rate", rule.status), rule.rate)?;
+            fraction(format!("status:{:?} probability", rule.status), rule.probability)?;
+        }
+        for promo in &self.promo_codes {
+            fraction(format!("promo:{} rate", promo.code), promo.rate)?;
+            fraction(format!("promo:{} usage", promo.code), promo.usage)?;
+        }
+        for promo in &self.category_promotions {
+            fraction(format!("category:{} rate", promo.name), promo.rate)?;
+        }
+        return fraction("max_discount".to_string(), self.max_discount);
+    }
+
+    pub fn apply(&self, customer: &Customer, product: &Product, date: DateTime<Utc>, rng: &mut impl Rng) -> AppliedDiscount {
+        let day = date.date_naive();
+        let mut best = AppliedDiscount { rate: 0.0, reason: "none".to_string() };
+        let mut consider = |rate: f64, reason: String| {
+            if rate > best.rate {
+                best = AppliedDiscount { rate, reason };
+            }
+        };
+
+        for rule in self.status_rates.iter().filter(|r| r.status == customer.status) {
+            if rng.random_bool(rule.probability.clamp(0.0, 1.0)) {
+                consider(rule.rate, format!("status:{:?}", rule.status));
+            }
+        }
+        for promo in &self.promo_codes {
+            let valid = promo.valid_from <= day && day <= promo.valid_to;
+            if valid && rng.random_bool(promo.usage.clamp(0.0, 1.0)) {
+                consider(promo.rate, format!("promo:{}", promo.code));
+            }
+        }
+        for promo in self.category_promotions.iter().filter(|p| p.category == product.category) {
+            if promo.valid_from <= day && day <= promo.valid_to {
+                consider(promo.rate, format!("category:{}", promo.name));
+            }
+        }
+
+        if best.rate > self.max_discount {
+            best.rate = self.max_discount;
+            best.reason.push_str(" (capped)");
+        }
+        return best;
+    }
+}
+
+fn always() -> f64 {
+    return 1.0;
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+use std::io;
+
+/// Anything that can stop a run, grouped by what went wrong so callers
+/// (and the scripts around the binary) can tell bad input from a failed write
+#[derive(Debug)]
+pub enum Error {
+    /// Bad arguments, or a config, policy, time model or spec file that won't load
+    Config(String),
+    /// The generator was asked for something it can't produce, like an unknown product
+    Generation(String),
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// Encoding a Parquet file failed
+    Parquet(arrow2::error::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The process exit code `main` reports this error with
+    pub fn exit_code(&self) -> i32 {
+        return match self {
+            Error::Config(_) => 2,
+            Error::Generation(_) => 3,
+            Error::Io(_) => 4,
+            Error::Parquet(_) => 5,
+        };
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Error::Config(msg) => write!(f, "{msg}"),
+            Error::Generation(msg) => write!(f, "Generation failed: {msg}"),
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Parquet(err) => write!(f, "Parquet error: {err}"),
+        };
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            Error::Io(err) => Some(err),
+            Error::Parquet(err) => Some(err),
+            Error::Config(_) | Error::Generation(_) => None,
+        };
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        return Error::Io(err);
+    }
+}
+
+impl From<arrow2::error::Error> for Error {
+    fn from(err: arrow2::error::Error) -> Self {
+        return match err {
+            arrow2::error::Error::Io(err) => Error::Io(err),
+            err => Error::Parquet(err),
+        };
+    }
+}
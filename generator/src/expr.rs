@@ -0,0 +1,827 @@
+use std::collections::HashMap;
+
+use arrow2::array::{Array, BooleanArray, Float64Array, Int64Array, PrimitiveArray, Utf8Array};
+use arrow2::compute::arity::{binary, binary_checked, unary};
+use arrow2::compute::boolean;
+use arrow2::compute::comparison;
+use arrow2::compute::if_then_else::if_then_else;
+use arrow2::datatypes::{DataType, TimeUnit};
+use arrow2::types::NativeType;
+use chrono::{DateTime, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// A derived column, e.g. `round(price * quantity * (1 - discount), 2)`,
+/// `date + days(3)` or `case when total >= 75 then "free" else "paid" end`.
+/// Evaluated a whole column at a time over the Arrow arrays of the columns it uses.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Expr {
+    /// As written in the spec
+    text: String,
+    root: Node,
+}
+
+/// What an expression evaluates to. Intervals only exist inside expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExprType {
+    Int,
+    Float,
+    Bool,
+    String,
+    Date,
+    Timestamp,
+    Interval,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+    Column(String),
+    Negate(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    /// `case when <cond> then <value> ... else <value> end`
+    Case { branches: Vec<(Node, Node)>, otherwise: Box<Node> },
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    /// `round(x)` or `round(x, digits)`
+    Round,
+    Abs,
+    /// Any number of values of any type, as one string
+    Concat,
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+    /// The date of a timestamp
+    Date,
+    Int,
+    Float,
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Expr, String> {
+        let mut parser = Parser { tokens: tokenize(text)?, pos: 0 };
+        let root = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {token:?} in expression: {text}"));
+        }
+        return Ok(Expr { text: text.to_string(), root });
+    }
+
+    /// The type the expression evaluates to, given the types of the columns it may use
+    pub fn check(&self, columns: &HashMap<&str, ExprType>) -> Result<ExprType, String> {
+        return self.root.check(columns);
+    }
+
+    /// Evaluate over `rows` rows of `columns`. The expression must have been
+    /// checked against columns of the same types.
+    pub fn evaluate(&self, columns: &HashMap<&str, &dyn Array>, rows: usize) -> Box<dyn Array> {
+        return self.root.evaluate(columns, rows);
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Expr, String> {
+        return Expr::parse(&text);
+    }
+}
+
+impl From<Expr> for String {
+    fn from(expr: Expr) -> String {
+        return expr.text;
+    }
+}
+
+impl ExprType {
+    pub fn data_type(self) -> DataType {
+        return match self {
+            ExprType::Int => DataType::Int64,
+            ExprType::Float => DataType::Float64,
+            ExprType::Bool => DataType::Boolean,
+            ExprType::String => DataType::Utf8,
+            ExprType::Date => DataType::Date32,
+            ExprType::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".to_string())),
+            ExprType::Interval => DataType::Duration(TimeUnit::Millisecond),
+        };
+    }
+
+    fn of(data_type: &DataType) -> ExprType {
+        return match data_type {
+            DataType::Int64 => ExprType::Int,
+            DataType::Float64 => ExprType::Float,
+            DataType::Boolean => ExprType::Bool,
+            DataType::Utf8 => ExprType::String,
+            DataType::Date32 => ExprType::Date,
+            DataType::Timestamp(..) => ExprType::Timestamp,
+            DataType::Duration(_) => ExprType::Interval,
+            other => panic!("Unsupported type in an expression: {other:?}"),
+        };
+    }
+
+    fn numeric(self) -> bool {
+        return matches!(self, ExprType::Int | ExprType::Float);
+    }
+}
+
+// ------------------------ Type checking ------------------------
+
+impl Node {
+    fn check(&self, columns: &HashMap<&str, ExprType>) -> Result<ExprType, String> {
+        return match self {
+            Node::Int(_) => Ok(ExprType::Int),
+            Node::Float(_) => Ok(ExprType::Float),
+            Node::Bool(_) => Ok(ExprType::Bool),
+            Node::Text(_) => Ok(ExprType::String),
+            Node::Column(name) => {
+                columns
+                    .get(name.as_str())
+                    .copied()
+                    .ok_or_else(|| format!("Unknown column {name}; an expression can use the columns before it"))
+            }
+            Node::Negate(inner) => match inner.check(columns)? {
+                t @ (ExprType::Int | ExprType::Float | ExprType::Interval) => Ok(t),
+                other => Err(format!("Can't negate {other:?}")),
+            },
+            Node::Not(inner) => match inner.check(columns)? {
+                ExprType::Bool => Ok(ExprType::Bool),
+                other => Err(format!("Can't apply not to {other:?}")),
+            },
+            Node::Binary(op, lhs, rhs) => binary_type(*op, lhs.check(columns)?, rhs.check(columns)?),
+            Node::Case { branches, otherwise } => {
+                let mut result = otherwise.check(columns)?;
+                for (condition, value) in branches {
+                    if condition.check(columns)? != ExprType::Bool {
+                        return Err("A case condition must be a bool".to_string());
+                    }
+                    result = match (result, value.check(columns)?) {
+                        (a, b) if a == b => a,
+                        (a, b) if a.numeric() && b.numeric() => ExprType::Float,
+                        (a, b) => return Err(format!("Case branches mix {a:?} and {b:?}")),
+                    };
+                }
+                Ok(result)
+            }
+            Node::Call(function, args) => {
+                let types = args.iter().map(|arg| arg.check(columns)).collect::<Result<Vec<_>, _>>()?;
+                call_type(*function, &types, args)
+            }
+        };
+    }
+}
+
+fn binary_type(op: BinaryOp, left: ExprType, right: ExprType) -> Result<ExprType, String> {
+    use ExprType::*;
+    let result = match op {
+        BinaryOp::And | BinaryOp::Or => (left == Bool && right == Bool).then_some(Bool),
+        BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq => {
+            (left == right || left.numeric() && right.numeric()).then_some(Bool)
+        }
+        _ if left.numeric() && right.numeric() => {
+            if op == BinaryOp::Div || left == Float || right == Float { Some(Float) }
+            else { Some(Int) }
+        }
+        BinaryOp::Add => match (left, right) {
+            (Timestamp, Interval) | (Interval, Timestamp) => Some(Timestamp),
+            (Date, Interval) | (Interval, Date) => Some(Date),
+            (Interval, Interval) => Some(Interval),
+            _ => None,
+        },
+        BinaryOp::Sub => match (left, right) {
+            (Timestamp, Interval) => Some(Timestamp),
+            (Date, Interval) => Some(Date),
+            (Timestamp, Timestamp) | (Interval, Interval) => Some(Interval),
+            // Whole days between the dates
+            (Date, Date) => Some(Int),
+            _ => None,
+        },
+        BinaryOp::Mul => match (left, right) {
+            (Interval, Int | Float) | (Int | Float, Interval) => Some(Interval),
+            _ => None,
+        },
+        _ => None,
+    };
+    return result.ok_or_else(|| format!("Can't apply {op:?} to {left:?} and {right:?}"));
+}
+
+fn call_type(function: Function, types: &[ExprType], args: &[Node]) -> Result<ExprType, String> {
+    use ExprType::*;
+    let arity_ok = match function {
+        Function::Round => types.len() == 1 || types.len() == 2 && matches!(args[1], Node::Int(_)),
+        Function::Concat => !types.is_empty(),
+        _ => types.len() == 1,
+    };
+    if !arity_ok {
+        return Err(format!("Wrong arguments to {function:?}; round takes a value and an optional whole number of digits"));
+    }
+    let result = match (function, types[0]) {
+        (Function::Round | Function::Abs, t) if t.numeric() => Some(t),
+        (Function::Concat, _) => types.iter().all(|t| *t != Interval).then_some(String),
+        (Function::Days | Function::Hours | Function::Minutes | Function::Seconds, t) if t.numeric() => Some(Interval),
+        (Function::Date, Date | Timestamp) => Some(Date),
+        (Function::Int, Int | Float | Bool) => Some(Int),
+        (Function::Float, t) if t.numeric() => Some(Float),
+        _ => None,
+    };
+    return result.ok_or_else(|| format!("Can't apply {function:?} to {types:?}"));
+}
+
+// ------------------------ Evaluation ------------------------
+
+impl Node {
+    fn evaluate(&self, columns: &HashMap<&str, &dyn Array>, rows: usize) -> Box<dyn Array> {
+        return match self {
+            Node::Int(value) => Box::new(Int64Array::from_vec(vec![*value; rows])),
+            Node::Float(value) => Box::new(Float64Array::from_vec(vec![*value; rows])),
+            Node::Bool(value) => Box::new(BooleanArray::from_slice(vec![*value; rows])),
+            Node::Text(value) => Box::new(Utf8Array::<i32>::from_iter_values(std::iter::repeat_n(value, rows))),
+            Node::Column(name) => columns[name.as_str()].to_boxed(),
+            Node::Negate(inner) => {
+                let array = inner.evaluate(columns, rows);
+                match ExprType::of(array.data_type()) {
+                    ExprType::Float => Box::new(unary(primitive::<f64>(array.as_ref()), |v| -v, DataType::Float64)),
+                    t => Box::new(unary(primitive::<i64>(array.as_ref()), |v| v.wrapping_neg(), t.data_type())),
+                }
+            }
+            Node::Not(inner) => Box::new(boolean::not(bools(inner.evaluate(columns, rows).as_ref()))),
+            Node::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(columns, rows), rhs.evaluate(columns, rows));
+                match op {
+                    BinaryOp::And => Box::new(boolean::and(bools(lhs.as_ref()), bools(rhs.as_ref()))),
+                    BinaryOp::Or => Box::new(boolean::or(bools(lhs.as_ref()), bools(rhs.as_ref()))),
+                    BinaryOp::Eq | BinaryOp::NotEq | BinaryOp::Lt | BinaryOp::LtEq | BinaryOp::Gt | BinaryOp::GtEq => {
+                        Box::new(compare(*op, lhs, rhs))
+                    }
+                    _ => arithmetic(*op, lhs.as_ref(), rhs.as_ref()),
+                }
+            }
+            Node::Case { branches, otherwise } => {
+                let mut values: Vec<Box<dyn Array>> = branches.iter().map(|(_, value)| value.evaluate(columns, rows)).collect();
+                values.push(otherwise.evaluate(columns, rows));
+                let types: Vec<ExprType> = values.iter().map(|v| ExprType::of(v.data_type())).collect();
+                if types.contains(&ExprType::Float) && types.contains(&ExprType::Int) {
+                    values = values.iter().map(|v| Box::new(floats(v.as_ref())) as Box<dyn Array>).collect();
+                }
+                // Later branches first, so the first condition that holds wins
+                let mut result = values.pop().unwrap();
+                for ((condition, _), value) in branches.iter().zip(values).rev() {
+                    let condition = definite(bools(condition.evaluate(columns, rows).as_ref()));
+                    result = if_then_else(&condition, value.as_ref(), result.as_ref()).unwrap();
+                }
+                result
+            }
+            Node::Call(function, args) => call(*function, args, columns, rows),
+        };
+    }
+}
+
+fn arithmetic(op: BinaryOp, lhs: &dyn Array, rhs: &dyn Array) -> Box<dyn Array> {
+    use ExprType::*;
+    let (left, right) = (ExprType::of(lhs.data_type()), ExprType::of(rhs.data_type()));
+    let result = binary_type(op, left, right).expect(CHECKED);
+    return match (left, right) {
+        _ if result == Float => Box::new(binary(&floats(lhs), &floats(rhs), DataType::Float64, float_op(op))),
+        (Int, Int) if op == BinaryOp::Rem => {
+            Box::new(binary_checked(primitive::<i64>(lhs), primitive::<i64>(rhs), DataType::Int64, |a, b| a.checked_rem(b)))
+        }
+        (Int, Int) | (Timestamp | Interval, Timestamp | Interval) => {
+            Box::new(binary(primitive::<i64>(lhs), primitive::<i64>(rhs), result.data_type(), int_op(op)))
+        }
+        (Date, Interval) => {
+            let days = unary(primitive::<i64>(rhs), |ms| ms.div_euclid(MILLIS_PER_DAY) as i32, DataType::Int32);
+            let shift: fn(i32, i32) -> i32 = if op == BinaryOp::Add { i32::wrapping_add } else { i32::wrapping_sub };
+            Box::new(binary(primitive::<i32>(lhs), &days, DataType::Date32, shift))
+        }
+        (Date, Date) => {
+            let days = binary(primitive::<i32>(lhs), primitive::<i32>(rhs), DataType::Int32, i32::wrapping_sub);
+            Box::new(unary(&days, |d| d as i64, DataType::Int64))
+        }
+        (Interval, Int | Float) => {
+            let scale = |ms: i64, factor: f64| (ms as f64 * factor).round() as i64;
+            Box::new(binary(primitive::<i64>(lhs), &floats(rhs), result.data_type(), scale))
+        }
+        // Only addition and multiplication get here, so the sides can swap
+        (Interval, Date) | (Int | Float, Interval) => arithmetic(op, rhs, lhs),
+        _ => unreachable!("{CHECKED}"),
+    };
+}
+
+fn int_op(op: BinaryOp) -> fn(i64, i64) -> i64 {
+    return match op {
+        BinaryOp::Add => i64::wrapping_add,
+        BinaryOp::Sub => i64::wrapping_sub,
+        BinaryOp::Mul => i64::wrapping_mul,
+        _ => unreachable!("{CHECKED}"),
+    };
+}
+
+fn float_op(op: BinaryOp) -> fn(f64, f64) -> f64 {
+    return match op {
+        BinaryOp::Add => |a, b| a + b,
+        BinaryOp::Sub => |a, b| a - b,
+        BinaryOp::Mul => |a, b| a * b,
+        BinaryOp::Div => |a, b| a / b,
+        BinaryOp::Rem => |a, b| a % b,
+        _ => unreachable!("{CHECKED}"),
+    };
+}
+
+fn compare(op: BinaryOp, lhs: Box<dyn Array>, rhs: Box<dyn Array>) -> BooleanArray {
+    // Mixed ints and floats compare as floats
+    let (lhs, rhs): (Box<dyn Array>, Box<dyn Array>) = {
+        if lhs.data_type() == rhs.data_type() { (lhs, rhs) }
+        else { (Box::new(floats(lhs.as_ref())), Box::new(floats(rhs.as_ref()))) }
+    };
+    let (lhs, rhs) = (lhs.as_ref(), rhs.as_ref());
+    return match op {
+        BinaryOp::Eq => comparison::eq(lhs, rhs),
+        BinaryOp::NotEq => comparison::neq(lhs, rhs),
+        BinaryOp::Lt => comparison::lt(lhs, rhs),
+        BinaryOp::LtEq => comparison::lt_eq(lhs, rhs),
+        BinaryOp::Gt => comparison::gt(lhs, rhs),
+        BinaryOp::GtEq => comparison::gt_eq(lhs, rhs),
+        _ => unreachable!("{CHECKED}"),
+    };
+}
+
+fn call(function: Function, args: &[Node], columns: &HashMap<&str, &dyn Array>, rows: usize) -> Box<dyn Array> {
+    let values: Vec<Box<dyn Array>> = args.iter().map(|arg| arg.evaluate(columns, rows)).collect();
+    let first = values[0].as_ref();
+    let kind = ExprType::of(first.data_type());
+    return match function {
+        Function::Round if kind == ExprType::Float => {
+            let digits = if let Some(Node::Int(digits)) = args.get(1) { *digits as i32 } else { 0 };
+            let factor = 10f64.powi(digits);
+            Box::new(unary(primitive::<f64>(first), |v| (v * factor).round() / factor, DataType::Float64))
+        }
+        Function::Abs if kind == ExprType::Float => Box::new(unary(primitive::<f64>(first), f64::abs, DataType::Float64)),
+        Function::Abs => Box::new(unary(primitive::<i64>(first), i64::wrapping_abs, DataType::Int64)),
+        Function::Round | Function::Int if kind == ExprType::Int => first.to_boxed(),
+        Function::Concat => {
+            let parts: Vec<Utf8Array<i32>> = values.iter().map(|v| strings(v.as_ref())).collect();
+            let joined: Utf8Array<i32> = {
+                (0..rows)
+                    .map(|i| {
+                        if parts.iter().any(|p| p.is_null(i)) { None }
+                        else { Some(parts.iter().map(|p| p.value(i)).collect::<String>()) }
+                    })
+                    .collect()
+            };
+            Box::new(joined)
+        }
+        Function::Days | Function::Hours | Function::Minutes | Function::Seconds => {
+            let unit = match function {
+                Function::Days => MILLIS_PER_DAY,
+                Function::Hours => 3_600_000,
+                Function::Minutes => 60_000,
+                _ => 1000,
+            };
+            let interval = ExprType::Interval.data_type();
+            Box::new(unary(&floats(first), move |v| (v * unit as f64).round() as i64, interval))
+        }
+        Function::Date if kind == ExprType::Timestamp => {
+            Box::new(unary(primitive::<i64>(first), |ms| ms.div_euclid(MILLIS_PER_DAY) as i32, DataType::Date32))
+        }
+        Function::Date => first.to_boxed(),
+        Function::Int if kind == ExprType::Bool => {
+            Box::new(bools(first).iter().map(|b| b.map(i64::from)).collect::<Int64Array>())
+        }
+        Function::Int => Box::new(unary(primitive::<f64>(first), |v| v as i64, DataType::Int64)),
+        Function::Float => Box::new(floats(first)),
+        Function::Round => unreachable!("{CHECKED}"),
+    };
+}
+
+// ------------------------ Array helpers ------------------------
+
+fn primitive<T: NativeType>(array: &dyn Array) -> &PrimitiveArray<T> {
+    return array.as_any().downcast_ref::<PrimitiveArray<T>>().expect(CHECKED);
+}
+
+fn bools(array: &dyn Array) -> &BooleanArray {
+    return array.as_any().downcast_ref::<BooleanArray>().expect(CHECKED);
+}
+
+fn floats(array: &dyn Array) -> Float64Array {
+    return match array.data_type() {
+        DataType::Float64 => primitive::<f64>(array).clone(),
+        _ => unary(primitive::<i64>(array), |v| v as f64, DataType::Float64),
+    };
+}
+
+/// Values as text; dates as `YYYY-MM-DD` and timestamps in RFC 3339, like the rest of the output
+fn strings(array: &dyn Array) -> Utf8Array<i32> {
+    fn each<T: NativeType>(array: &dyn Array, format: impl Fn(T) -> String) -> Utf8Array<i32> {
+        return primitive::<T>(array).iter().map(|v| v.map(|v| format(*v))).collect();
+    }
+    return match ExprType::of(array.data_type()) {
+        ExprType::String => array.as_any().downcast_ref::<Utf8Array<i32>>().expect(CHECKED).clone(),
+        ExprType::Bool => bools(array).iter().map(|b| b.map(|b| b.to_string())).collect(),
+        ExprType::Int => each(array, |v: i64| v.to_string()),
+        ExprType::Float => each(array, |v: f64| v.to_string()),
+        ExprType::Date => each(array, |days: i32| (NaiveDate::default() + Duration::days(days as i64)).to_string()),
+        ExprType::Timestamp => each(array, |ms: i64| DateTime::from_timestamp_millis(ms).unwrap_or_default().to_rfc3339()),
+        ExprType::Interval => unreachable!("{CHECKED}"),
+    };
+}
+
+/// Null conditions count as false
+fn definite(condition: &BooleanArray) -> BooleanArray {
+    return match condition.validity() {
+        Some(validity) => BooleanArray::new(DataType::Boolean, condition.values() & validity, None),
+        None => condition.clone(),
+    };
+}
+
+// ------------------------ Parsing ------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Word(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        }
+        else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            tokens.push({
+                if number.contains('.') { Token::Float(number.parse().map_err(|_| format!("Invalid number: {number}"))?) }
+                else { Token::Int(number.parse().map_err(|_| format!("Invalid number: {number}"))?) }
+            });
+        }
+        else if c == '"' || c == '\'' {
+            let Some(len) = chars[i + 1..].iter().position(|&q| q == c)
+            else { return Err(format!("Unterminated string in expression: {text}")); };
+            tokens.push(Token::Text(chars[i + 1..i + 1 + len].iter().collect()));
+            i += len + 2;
+        }
+        else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        }
+        else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s))
+            else { return Err(format!("Unexpected '{c}' in expression: {text}")); };
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    return Ok(tokens);
+}
+
+/// Recursive descent, loosest binding first: or, and, not, comparison,
+/// addition, multiplication, negation
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.eat_keyword("or") {
+            node = Node::Binary(BinaryOp::Or, Box::new(node), Box::new(self.and()?));
+        }
+        return Ok(node);
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.not()?;
+        while self.eat_keyword("and") {
+            node = Node::Binary(BinaryOp::And, Box::new(node), Box::new(self.not()?));
+        }
+        return Ok(node);
+    }
+
+    fn not(&mut self) -> Result<Node, String> {
+        if self.eat_keyword("not") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        return self.comparison();
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        let node = self.sum()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("==" | "=")) => BinaryOp::Eq,
+            Some(Token::Symbol("!=" | "<>")) => BinaryOp::NotEq,
+            Some(Token::Symbol("<=")) => BinaryOp::LtEq,
+            Some(Token::Symbol(">=")) => BinaryOp::GtEq,
+            Some(Token::Symbol("<")) => BinaryOp::Lt,
+            Some(Token::Symbol(">")) => BinaryOp::Gt,
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+        return Ok(Node::Binary(op, Box::new(node), Box::new(self.sum()?)));
+    }
+
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOp::Add,
+                Some(Token::Symbol("-")) => BinaryOp::Sub,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.negation()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOp::Mul,
+                Some(Token::Symbol("/")) => BinaryOp::Div,
+                Some(Token::Symbol("%")) => BinaryOp::Rem,
+                _ => return Ok(node),
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.negation()?));
+        }
+    }
+
+    fn negation(&mut self) -> Result<Node, String> {
+        if self.eat_symbol("-") {
+            return Ok(match self.negation()? {
+                Node::Int(value) => Node::Int(-value),
+                Node::Float(value) => Node::Float(-value),
+                other => Node::Negate(Box::new(other)),
+            });
+        }
+        return self.primary();
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        let Some(token) = self.tokens.get(self.pos).cloned()
+        else { return Err("Expression ends too early".to_string()); };
+        self.pos += 1;
+        return match token {
+            Token::Int(value) => Ok(Node::Int(value)),
+            Token::Float(value) => Ok(Node::Float(value)),
+            Token::Text(value) => Ok(Node::Text(value)),
+            Token::Symbol("(") => {
+                let node = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(node)
+            }
+            Token::Word(word) => match word.to_lowercase().as_str() {
+                "true" => Ok(Node::Bool(true)),
+                "false" => Ok(Node::Bool(false)),
+                "case" => self.case(),
+                "and" | "or" | "not" | "when" | "then" | "else" | "end" => Err(format!("Unexpected {word}")),
+                name if self.eat_symbol("(") => {
+                    let function = function(name)?;
+                    let mut args = vec![self.expr()?];
+                    while self.eat_symbol(",") {
+                        args.push(self.expr()?);
+                    }
+                    self.expect_symbol(")")?;
+                    Ok(Node::Call(function, args))
+                }
+                _ => Ok(Node::Column(word)),
+            },
+            other => Err(format!("Unexpected {other:?}")),
+        };
+    }
+
+    /// After `case`
+    fn case(&mut self) -> Result<Node, String> {
+        let mut branches = Vec::new();
+        while self.eat_keyword("when") {
+            let condition = self.expr()?;
+            if !self.eat_keyword("then") {
+                return Err("Expected then after a case condition".to_string());
+            }
+            branches.push((condition, self.expr()?));
+        }
+        if branches.is_empty() || !self.eat_keyword("else") {
+            return Err("A case needs at least one when and an else".to_string());
+        }
+        let otherwise = Box::new(self.expr()?);
+        if !self.eat_keyword("end") {
+            return Err("Expected end after a case".to_string());
+        }
+        return Ok(Node::Case { branches, otherwise });
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.pos);
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        return found;
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.pos += 1;
+        }
+        return found;
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_symbol(symbol) {
+            return Ok(());
+        }
+        return Err(format!("Expected '{symbol}', found {:?}", self.peek()));
+    }
+}
+
+fn function(name: &str) -> Result<Function, String> {
+    return match name {
+        "round" => Ok(Function::Round),
+        "abs" => Ok(Function::Abs),
+        "concat" => Ok(Function::Concat),
+        "days" => Ok(Function::Days),
+        "hours" => Ok(Function::Hours),
+        "minutes" => Ok(Function::Minutes),
+        "seconds" => Ok(Function::Seconds),
+        "date" => Ok(Function::Date),
+        "int" => Ok(Function::Int),
+        "float" => Ok(Function::Float),
+        other => Err(format!("Unknown function: {other}")),
+    };
+}
+
+// ------------------------ Static Data ------------------------
+
+/// Longest first, so `<=` isn't read as `<`
+const SYMBOLS: [&str; 16] = ["==", "!=", "<>", "<=", ">=", "=", "<", ">", "+", "-", "*", "/", "%", "(", ")", ","];
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+const CHECKED: &str = "expressions are type checked before they are evaluated";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow2::array::Int32Array;
+
+    fn day(y: i32, m: u32, d: u32) -> i32 {
+        return (NaiveDate::from_ymd_opt(y, m, d).unwrap() - NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as i32;
+    }
+
+    /// Check `text` against the test columns, then evaluate it over their three rows
+    fn eval(text: &str) -> (ExprType, Box<dyn Array>) {
+        let a = Int64Array::from_vec(vec![1, 4, 7]);
+        let b = Int64Array::from_vec(vec![2, 5, 8]);
+        let c = Int64Array::from_vec(vec![3, 6, 9]);
+        let x = Float64Array::from_vec(vec![1.5, 4.0, 1234.5]);
+        let zero = Int64Array::from_vec(vec![0, 0, 0]);
+        let yes = BooleanArray::from_slice([true, true, true]);
+        let no = BooleanArray::from_slice([false, false, false]);
+        let flag = BooleanArray::from([Some(true), None, Some(false)]);
+        let shipped = Int32Array::from_vec(vec![day(2024, 3, 1), day(2024, 2, 28), day(2025, 1, 1)]).to(DataType::Date32);
+        let ordered = Int32Array::from_vec(vec![day(2024, 2, 20), day(2024, 2, 28), day(2024, 12, 25)]).to(DataType::Date32);
+        let arrays: Vec<(&str, &dyn Array)> = vec![
+            ("a", &a), ("b", &b), ("c", &c), ("x", &x), ("zero", &zero),
+            ("yes", &yes), ("no", &no), ("flag", &flag), ("shipped", &shipped), ("ordered", &ordered),
+        ];
+        let types = arrays.iter().map(|(name, array)| (*name, ExprType::of(array.data_type()))).collect();
+        let expr = Expr::parse(text).unwrap();
+        let kind = expr.check(&types).unwrap();
+        let array = expr.evaluate(&arrays.into_iter().collect(), 3);
+        assert_eq!(array.data_type(), &kind.data_type());
+        return (kind, array);
+    }
+
+    fn ints(array: &dyn Array) -> Vec<Option<i64>> {
+        return primitive::<i64>(array).iter().map(|v| v.copied()).collect();
+    }
+
+    fn values<T: NativeType>(array: &dyn Array) -> Vec<T> {
+        return primitive::<T>(array).values().to_vec();
+    }
+
+    fn truths(array: &dyn Array) -> Vec<Option<bool>> {
+        return bools(array).iter().collect();
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let (kind, array) = eval("a + b * c");
+        assert_eq!(kind, ExprType::Int);
+        assert_eq!(values::<i64>(array.as_ref()), vec![7, 34, 79]);
+        assert_eq!(values::<i64>(eval("(a + b) * c").1.as_ref()), vec![9, 54, 135]);
+        assert_eq!(values::<i64>(eval("a - b - c").1.as_ref()), vec![-4, -7, -10]);
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        // (not no) and no, rather than not (no and no)
+        assert_eq!(truths(eval("not no and no").1.as_ref()), vec![Some(false); 3]);
+        assert_eq!(truths(eval("not (no and no)").1.as_ref()), vec![Some(true); 3]);
+        assert_eq!(truths(eval("no and no or yes").1.as_ref()), vec![Some(true); 3]);
+        assert_eq!(truths(eval("a < b and b < c").1.as_ref()), vec![Some(true); 3]);
+    }
+
+    #[test]
+    fn case_treats_null_conditions_as_false() {
+        let (_, array) = eval("case when flag then a else b end");
+        assert_eq!(ints(array.as_ref()), vec![Some(1), Some(5), Some(8)]);
+        let (_, array) = eval("case when flag then 1 when not flag then 2 else 3 end");
+        assert_eq!(ints(array.as_ref()), vec![Some(1), Some(3), Some(2)]);
+    }
+
+    #[test]
+    fn case_promotes_mixed_ints_and_floats() {
+        let (kind, array) = eval("case when a > 3 then x else a end");
+        assert_eq!(kind, ExprType::Float);
+        assert_eq!(values::<f64>(array.as_ref()), vec![1.0, 4.0, 1234.5]);
+    }
+
+    #[test]
+    fn comparisons_promote_mixed_ints_and_floats() {
+        assert_eq!(truths(eval("a < x").1.as_ref()), vec![Some(true), Some(false), Some(true)]);
+        assert_eq!(truths(eval("a = x").1.as_ref()), vec![Some(false), Some(true), Some(false)]);
+        let (kind, array) = eval("a / b");
+        assert_eq!(kind, ExprType::Float);
+        assert_eq!(values::<f64>(array.as_ref()), vec![0.5, 0.8, 0.875]);
+    }
+
+    #[test]
+    fn dates_shift_by_days_and_subtract_to_days() {
+        let (kind, array) = eval("ordered + days(10)");
+        assert_eq!(kind, ExprType::Date);
+        assert_eq!(values::<i32>(array.as_ref()), vec![day(2024, 3, 1), day(2024, 3, 9), day(2025, 1, 4)]);
+        assert_eq!(values::<i32>(eval("days(1) + ordered").1.as_ref()), vec![day(2024, 2, 21), day(2024, 2, 29), day(2024, 12, 26)]);
+
+        let (kind, array) = eval("shipped - ordered");
+        assert_eq!(kind, ExprType::Int);
+        assert_eq!(values::<i64>(array.as_ref()), vec![10, 0, 7]);
+    }
+
+    #[test]
+    fn remainder_by_zero_is_null() {
+        assert_eq!(ints(eval("c % zero").1.as_ref()), vec![None; 3]);
+        assert_eq!(ints(eval("c % b").1.as_ref()), vec![Some(1), Some(1), Some(1)]);
+    }
+
+    #[test]
+    fn round_takes_negative_digits() {
+        assert_eq!(values::<f64>(eval("round(x, -1)").1.as_ref()), vec![0.0, 0.0, 1230.0]);
+        assert_eq!(values::<f64>(eval("round(x / 4, 2)").1.as_ref()), vec![0.38, 1.0, 308.63]);
+        assert_eq!(values::<i64>(eval("round(a)").1.as_ref()), vec![1, 4, 7]);
+    }
+
+    #[test]
+    fn parse_errors_say_what_went_wrong() {
+        let error = |text: &str| Expr::parse(text).unwrap_err();
+        assert!(error("concat(\"open, a)").contains("Unterminated string"));
+        assert!(error("median(a)").contains("Unknown function: median"));
+        assert!(error("a +").contains("ends too early"));
+        assert!(error("case when a > 1 then a end").contains("else"));
+        assert!(error("a b").contains("Unexpected"));
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+
+use crate::customers::{self, ContactOverride, Customer};
+use crate::orders::{self, Order, OrderContext};
+use crate::payments::Payment;
+use crate::products;
+use crate::timing;
+use crate::utils::{generate_datetime, generate_uuid};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum FraudPattern {
+    /// Many tiny orders in quick succession on one instrument
+    CardTesting,
+    /// Email and address changed, then high-value electronics shipped to the new address
+    AccountTakeover,
+    /// A burst of ordinary orders, far faster than the customer normally buys
+    VelocityAbuse,
+}
+
+/// Ground truth for one planted order
+#[derive(Debug, Clone)]
+pub struct FraudLabel {
+    pub order_id: String,
+    pub incident_id: String,
+    pub pattern: FraudPattern,
+    pub customer_id: i64,
+    pub instrument_id: String,
+    pub incident_start: DateTime<Utc>,
+}
+
+/// Everything planted by `plant_incidents`
+#[derive(Default)]
+pub struct FraudInjection {
+    pub orders: Vec<Order>,
+    pub labels: Vec<FraudLabel>,
+    pub incidents: usize,
+    /// Contact details changed by account takeovers, for the customer history
+    pub overrides: Vec<ContactOverride>,
+}
+
+struct Incident {
+    pattern: FraudPattern,
+    start: DateTime<Utc>,
+    orders: Vec<Order>,
+    takeover: Option<ContactOverride>,
+}
+
+/// Plant fraud incidents until they add up to `count` orders, settled as of
+/// `now`; the last incident may be cut short.
+pub fn plant_incidents(ctx: &OrderContext, count: usize, now: DateTime<Utc>, rng: &mut impl Rng) -> FraudInjection {
+    let mut planted = FraudInjection::default();
+    while planted.orders.len() < count {
+        let limit = count - planted.orders.len();
+        let incident = match rng.random_range(0..10) {
+            0..=3 => card_testing(ctx, limit, rng, now),
+            4..=6 => account_takeover(ctx, limit, rng, now),
+            _ => velocity_abuse(ctx, limit, rng, now),
+        };
+        let incident_id = generate_uuid(rng);
+        planted.labels.extend(incident.orders.iter().map(|order| FraudLabel {
+            order_id: order.id.clone(),
+            incident_id: incident_id.clone(),
+            pattern: incident.pattern,
+            customer_id: order.customer.id,
+            instrument_id: order.payment.instrument_id.clone(),
+            incident_start: incident.start,
+        }));
+        planted.orders.extend(incident.orders);
+        planted.overrides.extend(incident.takeover);
+        planted.incidents += 1;
+    }
+    return planted;
+}
+
+/// A fraudster checks whether a card works with a run of tiny purchases,
+/// seconds apart. Issuers catch many of them.
+fn card_testing(ctx: &OrderContext, limit: usize, rng: &mut impl Rng, now: DateTime<Utc>) -> Incident {
+    let (customer, start) = victim(ctx, rng);
+    let card = ctx.instruments.new_payment(&customer, start, rng);
+    let size = rng.random_range(10..=40).min(limit);
+
+    let mut date = start;
+    let mut orders = Vec::with_capacity(size);
+    for _ in 0..size {
+        date += Duration::seconds(rng.random_range(5..90));
+        let product = products::product_at(CARD_TESTING_PRODUCTS[rng.random_range(0..CARD_TESTING_PRODUCTS.len())]);
+        let payment = Payment { transaction_id: generate_uuid(rng), ..card.clone() };
+        let mut order = orders::new_order(generate_uuid(rng), customer.clone(), product, 1, date, payment);
+        orders::settle(&mut order, ctx, CARD_TESTING_RISK, rng, now);
+        orders.push(order);
+    }
+    return Incident { pattern: FraudPattern::CardTesting, start, orders, takeover: None };
+}
+
+/// Someone takes over an account, points its email and address at
+/// themselves, and orders expensive electronics on the saved instruments
+/// until the customer recovers the account.
+fn account_takeover(ctx: &OrderContext, limit: usize, rng: &mut impl Rng, now: DateTime<Utc>) -> Incident {
+    let (mut customer, start) = victim(ctx, rng);
+    customer.email = attacker_email(rng);
+    customer.address = customers::random_address(&customer.country, rng);
+    customer.timezone = timing::timezone_for(&customer.country, &customer.address).to_string();
+    let recovered = start + Duration::days(rng.random_range(2..14));
+    let takeover = ContactOverride {
+        customer_id: customer.id,
+        email: customer.email.clone(),
+        address: customer.address.clone(),
+        timezone: customer.timezone.clone(),
+        from: start,
+        to: (recovered < ctx.end).then_some(recovered),
+    };
+
+    let size = rng.random_range(2..=6).min(limit);
+    let mut date = start;
+    let mut orders = Vec::with_capacity(size);
+    for _ in 0..size {
+        date += Duration::minutes(rng.random_range(20..6 * 60));
+        let product = products::product_at(TAKEOVER_PRODUCTS[rng.random_range(0..TAKEOVER_PRODUCTS.len())]);
+        let payment = ctx.instruments.new_payment(&customer, date, rng);
+        let quantity = rng.random_range(1..=3);
+        let mut order = orders::new_order(generate_uuid(rng), customer.clone(), product, quantity, date, payment);
+        orders::settle(&mut order, ctx, TAKEOVER_RISK, rng, now);
+        orders.push(order);
+    }
+    return Incident { pattern: FraudPattern::AccountTakeover, start, orders, takeover: Some(takeover) };
+}
+
+/// A burst of ordinary-looking orders a few minutes apart, cycling through
+/// the account's instruments
+fn velocity_abuse(ctx: &OrderContext, limit: usize, rng: &mut impl Rng, now: DateTime<Utc>) -> Incident {
+    let (customer, start) = victim(ctx, rng);
+    let size = rng.random_range(8..=25).min(limit);
+
+    let mut date = start;
+    let mut orders = Vec::with_capacity(size);
+    for _ in 0..size {
+        date += Duration::minutes(rng.random_range(1..10));
+        let product = products::generate_product(rng);
+        let payment = ctx.instruments.new_payment(&customer, date, rng);
+        let quantity = rng.random_range(1..=3);
+        let mut order = orders::new_order(generate_uuid(rng), customer.clone(), product, quantity, date, payment);
+        orders::settle(&mut order, ctx, VELOCITY_RISK, rng, now);
+        orders.push(order);
+    }
+    return Incident { pattern: FraudPattern::VelocityAbuse, start, orders, takeover: None };
+}
+
+/// A random customer, as of a random start time that leaves room for the
+/// incident to play out before the end of the period
+fn victim(ctx: &OrderContext, rng: &mut impl Rng) -> (Customer, DateTime<Utc>) {
+    let latest = (ctx.end - Duration::days(2)).max(ctx.start + Duration::hours(1));
+    let start = generate_datetime(ctx.start, latest, rng);
+    let mut customer = customers::generate_customer(ctx.customers, rng);
+    if let Some(history) = ctx.history {
+        customer = history.customer_at(&customer, start).clone();
+    }
+    return (customer, start);
+}
+
+fn attacker_email(rng: &mut impl Rng) -> String {
+    let user: String = (0..rng.random_range(6..10)).map(|_| rng.random_range(b'a'..=b'z') as char).collect();
+    let domain = ATTACKER_DOMAINS[rng.random_range(0..ATTACKER_DOMAINS.len())];
+    return format!("{user}{}@{domain}", rng.random_range(10..1000));
+}
+
+// ------------------------ Static Data ------------------------
+
+/// Cheap items: canned beans, rice, hand sanitizer, notebook, USB-C cable, air freshener
+const CARD_TESTING_PRODUCTS: [usize; 6] = [2, 3, 6, 15, 27, 49];
+/// Bluetooth speaker and noise-canceling headphones
+const TAKEOVER_PRODUCTS: [usize; 2] = [26, 28];
+const ATTACKER_DOMAINS: [&str; 4] = ["protonmail.com", "tutanota.com", "guerrillamail.com", "mail.ru"];
+
+/// Added to the chance an attempt is flagged as fraud
+const CARD_TESTING_RISK: f64 = 0.3;
+const TAKEOVER_RISK: f64 = 0.05;
+const VELOCITY_RISK: f64 = 0.02;
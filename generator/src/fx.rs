@@ -0,0 +1,112 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Daily exchange rates (local units per USD) for every supported currency
+/// over the simulated period. Each currency walks from `FX_EPOCH` with its
+/// own seed, so a calendar day gets the same rate in every batch, whatever
+/// period the batch covers.
+pub struct FxTable {
+    start: NaiveDate,
+    rates: Vec<(&'static str, Vec<f64>)>,
+}
+
+impl FxTable {
+    pub fn simulate(start: DateTime<Utc>, end: DateTime<Utc>) -> FxTable {
+        let start = start.date_naive();
+        let days = (end.date_naive() - start).num_days().max(0) as usize + 1;
+        let rates = CURRENCIES.iter().map(|(code, anchor, _)| (*code, walk(code, *anchor, start, days))).collect();
+        return FxTable { start, rates };
+    }
+
+    /// Local units per USD on the day of `date`
+    pub fn rate(&self, currency: &str, date: DateTime<Utc>) -> f64 {
+        let Some((_, series)) = self.rates.iter().find(|(code, _)| *code == currency) else {
+            return 1.0;
+        };
+        let day = (date.date_naive() - self.start).num_days().clamp(0, series.len() as i64 - 1);
+        return series[day as usize];
+    }
+
+    /// Convert a USD amount into `currency`, rounded to that currency's minor unit
+    pub fn convert(&self, usd: f64, currency: &str, date: DateTime<Utc>) -> f64 {
+        let scale = 10f64.powi(minor_digits(currency) as i32);
+        return (usd * self.rate(currency, date) * scale).round() / scale;
+    }
+
+    /// Every (date, currency, local units per USD) row of the table
+    pub fn rows(&self) -> impl Iterator<Item = (NaiveDate, &'static str, f64)> + '_ {
+        return self.rates.iter().flat_map(move |(code, series)| {
+            series
+                .iter()
+                .enumerate()
+                .map(move |(i, rate)| (self.start + Duration::days(i as i64), *code, *rate))
+        });
+    }
+}
+
+pub fn currency_for_country(country: &str) -> &'static str {
+    return match country {
+        "CA" => "CAD",
+        "GB" => "GBP",
+        "DE" | "FR" => "EUR",
+        "JP" => "JPY",
+        "MX" => "MXN",
+        "AU" => "AUD",
+        _ => "USD",
+    };
+}
+
+/// `days` daily rates from `start`. Days before `FX_EPOCH` sit at the anchor rate.
+fn walk(code: &str, anchor: f64, start: NaiveDate, days: usize) -> Vec<f64> {
+    let lead = (start - FX_EPOCH).num_days();
+    let mut series = vec![anchor; (-lead).clamp(0, days as i64) as usize];
+    let mut rng = StdRng::seed_from_u64(FX_SEED ^ currency_seed(code));
+    let mut rate = anchor;
+    for offset in 0..lead + days as i64 {
+        if code != "USD" {
+            // Small daily moves that drift back towards the anchor rate
+            let shock = rng.random_range(-1.0..1.0) * DAILY_VOLATILITY;
+            let pull = (anchor / rate).ln() * MEAN_REVERSION;
+            rate *= (shock + pull).exp();
+        }
+        if offset >= lead {
+            series.push(rate);
+        }
+    }
+    return series;
+}
+
+/// The currency code's bytes, so each currency has its own walk
+fn currency_seed(code: &str) -> u64 {
+    return code.bytes().fold(0, |seed, byte| seed << 8 | byte as u64);
+}
+
+fn minor_digits(currency: &str) -> u32 {
+    return {
+        CURRENCIES
+            .iter()
+            .find(|(code, _, _)| *code == currency)
+            .map(|(_, _, digits)| *digits)
+            .unwrap_or(2)
+    };
+}
+
+// ------------------------ Static Data ------------------------
+
+const FX_SEED: u64 = 0xF0_2E_C4;
+/// Where every walk starts
+const FX_EPOCH: NaiveDate = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+const DAILY_VOLATILITY: f64 = 0.006;
+const MEAN_REVERSION: f64 = 0.02;
+
+/// (code, anchor rate in local units per USD, minor unit digits)
+const CURRENCIES: [(&str, f64, u32); 7] = [
+    ("USD", 1.0, 2),
+    ("CAD", 1.37, 2),
+    ("GBP", 0.79, 2),
+    ("EUR", 0.92, 2),
+    ("JPY", 151.0, 0),
+    ("MXN", 17.1, 2),
+    ("AUD", 1.52, 2),
+];
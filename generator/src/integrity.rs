@@ -0,0 +1,185 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::customers::Customer;
+use crate::error::Result;
+use crate::orders::Order;
+use crate::products::Product;
+use crate::utils;
+
+/// Referential integrity violations to plant in the star schema output.
+/// The orders themselves are left alone; only what the writer emits changes.
+#[derive(Default)]
+pub struct IntegrityPlan {
+    /// Fact rows whose `customer_id` points at no customer, by order index
+    pub orphan_customers: HashMap<usize, i64>,
+    /// Fact rows whose `product_id` points at no product, by order index
+    pub orphan_products: HashMap<usize, i64>,
+    /// Dimension rows left out of this batch, to arrive with the next one
+    /// as they were in this batch
+    pub late_customers: BTreeMap<i64, Customer>,
+    pub late_products: BTreeMap<i64, Product>,
+    /// Dimension rows held back by the previous batch, written with this one
+    pub carried_customers: Vec<Customer>,
+    pub carried_products: Vec<Product>,
+}
+
+/// How many violations a plan planted, for the manifest
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct IntegrityCounts {
+    pub orphan_customers: usize,
+    pub orphan_products: usize,
+    pub late_customers: usize,
+    pub late_products: usize,
+    pub carried_customers: usize,
+    pub carried_products: usize,
+}
+
+/// Rows held back for the next batch, kept between runs in the output directory
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct HeldBack {
+    customers: Vec<Customer>,
+    products: Vec<Product>,
+}
+
+/// Point `orphan_rate` of the orders at a customer or product that is not in
+/// the dimensions (half each), and hold back `late_rate` of the batch's
+/// customers and products until the next batch. Rows held back by the
+/// previous batch are picked up from the output directory.
+pub fn plan(
+    orders: &[Order],
+    orphan_rate: f64,
+    late_rate: f64,
+    output_dir: &str,
+    rng: &mut impl Rng,
+) -> Result<IntegrityPlan> {
+    let held = load_held_back(&held_back_path(output_dir))?;
+    let mut plan = IntegrityPlan::default();
+
+    for index in 0..orders.len() {
+        if !rng.random_bool(orphan_rate) {
+            continue;
+        }
+        if rng.random_bool(0.5) {
+            // Some carry the -1 a failed customer lookup falls back to
+            let id = if rng.random_bool(0.5) { -1 } else { rng.random_range(UNKNOWN_IDS) };
+            plan.orphan_customers.insert(index, id);
+        }
+        else { plan.orphan_products.insert(index, rng.random_range(UNKNOWN_IDS)); }
+    }
+
+    if late_rate > 0.0 {
+        let (customers, products) = dimension_rows(orders);
+        // Rows arriving late from the last batch aren't held back again
+        plan.late_customers = {
+            customers
+                .into_iter()
+                .filter(|(id, _)| !held.customers.iter().any(|c| c.id == *id) && rng.random_bool(late_rate))
+                .map(|(id, customer)| (id, customer.clone()))
+                .collect()
+        };
+        plan.late_products = {
+            products
+                .into_iter()
+                .filter(|(id, _)| !held.products.iter().any(|p| p.id == *id) && rng.random_bool(late_rate))
+                .map(|(id, product)| (id, product.clone()))
+                .collect()
+        };
+    }
+    plan.carried_customers = held.customers;
+    plan.carried_products = held.products;
+    return Ok(plan);
+}
+
+/// The customer and product rows of a batch's dimensions, by id. A customer
+/// who appears in several versions keeps the one from their latest order.
+pub fn dimension_rows(orders: &[Order]) -> (BTreeMap<i64, &Customer>, BTreeMap<i64, &Product>) {
+    let mut latest: BTreeMap<i64, &Order> = BTreeMap::new();
+    let mut products: BTreeMap<i64, &Product> = BTreeMap::new();
+    for order in orders {
+        let entry = latest.entry(order.customer.id).or_insert(order);
+        if order.date > entry.date {
+            *entry = order;
+        }
+        products.entry(order.product.id).or_insert(&order.product);
+    }
+    let customers = latest.into_iter().map(|(id, o)| (id, &o.customer)).collect();
+    return (customers, products);
+}
+
+impl IntegrityPlan {
+    /// Remember the rows held back from this batch for the next run. Returns
+    /// the file written, if anything was held back.
+    pub fn save(&self, output_dir: &str) -> io::Result<Option<PathBuf>> {
+        let path = held_back_path(output_dir);
+        if self.late_customers.is_empty() && self.late_products.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(None);
+        }
+        let held = HeldBack {
+            customers: self.late_customers.values().cloned().collect(),
+            products: self.late_products.values().cloned().collect(),
+        };
+        utils::write_atomic(&path, |writer| serde_json::to_writer(writer, &held).map_err(io::Error::other))?;
+        return Ok(Some(path));
+    }
+
+    pub fn counts(&self) -> IntegrityCounts {
+        return IntegrityCounts {
+            orphan_customers: self.orphan_customers.len(),
+            orphan_products: self.orphan_products.len(),
+            late_customers: self.late_customers.len(),
+            late_products: self.late_products.len(),
+            carried_customers: self.carried_customers.len(),
+            carried_products: self.carried_products.len(),
+        };
+    }
+
+    pub fn report(&self) {
+        let counts = self.counts();
+        let planted = {
+            counts.orphan_customers + counts.orphan_products +
+            counts.late_customers + counts.late_products +
+            counts.carried_customers + counts.carried_products
+        };
+        if planted == 0 {
+            return;
+        }
+        println!(
+            "Orphaned {} orders by customer and {} by product; held back {} customers and {} products \
+             for the next batch; {} customers and {} products arrived late from the last batch.",
+            counts.orphan_customers,
+            counts.orphan_products,
+            counts.late_customers,
+            counts.late_products,
+            counts.carried_customers,
+            counts.carried_products,
+        );
+    }
+}
+
+// ------------------------ Persistence ------------------------
+
+fn held_back_path(output_dir: &str) -> PathBuf {
+    return Path::new(output_dir).join("late_dimensions.json");
+}
+
+fn load_held_back(path: &Path) -> io::Result<HeldBack> {
+    if !path.exists() {
+        return Ok(HeldBack::default());
+    }
+    let reader = BufReader::new(File::open(path)?);
+    return serde_json::from_reader(reader).map_err(io::Error::other);
+}
+
+// ------------------------ Static Data ------------------------
+
+/// Ids never issued to a customer or product
+const UNKNOWN_IDS: std::ops::Range<i64> = 9000..10_000;
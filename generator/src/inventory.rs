@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::orders::{Order, OrderStatus};
+use crate::products::PRODUCT_COUNT;
+
+/// End-of-day stock for one product
+#[derive(Debug, Clone)]
+pub struct InventorySnapshot {
+    pub date: NaiveDate,
+    pub product_id: i64,
+    pub on_hand: i64,
+    /// Units ordered from the supplier but not yet received
+    pub on_order: i64,
+    pub received: i64,
+    pub sold: i64,
+    /// Units owed to backordered orders
+    pub backordered: i64,
+    /// Whether any order went unfilled from stock that day
+    pub stockout: bool,
+}
+
+/// Reorder-point policy for one product, with supply measured in days of
+/// average demand
+struct StockPolicy {
+    lead_days: i64,
+    safety_days: f64,
+    cover_days: f64,
+}
+
+struct ProductStock {
+    policy: StockPolicy,
+    rng: StdRng,
+    daily_demand: f64,
+    on_hand: i64,
+    /// (arrival, units) for each open restock
+    inbound: Vec<(DateTime<Utc>, i64)>,
+    /// (order index, units) waiting for stock, oldest first
+    backorders: VecDeque<(usize, i64)>,
+}
+
+impl ProductStock {
+    fn reorder_point(&self) -> i64 {
+        let days = self.policy.lead_days as f64 + self.policy.safety_days;
+        return (self.daily_demand * days).ceil() as i64;
+    }
+
+    fn reorder_quantity(&self) -> i64 {
+        return (self.daily_demand * self.policy.cover_days).ceil().max(1.0) as i64;
+    }
+
+    fn on_order(&self) -> i64 {
+        return self.inbound.iter().map(|(_, units)| units).sum();
+    }
+
+    fn owed(&self) -> i64 {
+        return self.backorders.iter().map(|(_, units)| units).sum();
+    }
+}
+
+/// Walk the orders in date order (sorting them in place), filling each one
+/// from its product's stock. Orders that find the shelf empty are either
+/// backordered until the next restock or cancelled as "OutOfStock".
+/// Products restock from a supplier when stock drops to their reorder point.
+/// Returns a snapshot of every product at the end of every day.
+pub fn apply_inventory(orders: &mut [Order], start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<InventorySnapshot> {
+    orders.sort_by_key(|o| o.date);
+    let days = (end - start).num_days().max(1);
+    let mut demand = [0i64; PRODUCT_COUNT];
+    for order in orders.iter() {
+        demand[order.product.id as usize] += order.quantity as i64;
+    }
+    let mut stocks: Vec<ProductStock> = {
+        (0..PRODUCT_COUNT)
+            .map(|id| new_stock(id as i64, demand[id] as f64 / days as f64))
+            .collect()
+    };
+
+    let now = Utc::now();
+    let mut snapshots = Vec::with_capacity(PRODUCT_COUNT * days as usize);
+    let mut next = 0;
+    let mut day = start;
+    while day < end {
+        let day_end = day + Duration::days(1);
+        let mut received = [0i64; PRODUCT_COUNT];
+        let mut sold = [0i64; PRODUCT_COUNT];
+        let mut stockout = [false; PRODUCT_COUNT];
+
+        // Deliveries arrive first thing and go to backorders before the shelf
+        for (id, stock) in stocks.iter_mut().enumerate() {
+            let (arrived, inbound): (Vec<_>, Vec<_>) = stock.inbound.iter().partition(|(at, _)| *at < day_end);
+            stock.inbound = inbound;
+            for (at, units) in arrived {
+                stock.on_hand += units;
+                received[id] += units;
+                while let Some(&(index, units)) = stock.backorders.front() {
+                    if units > stock.on_hand {
+                        break;
+                    }
+                    stock.on_hand -= units;
+                    sold[id] += units;
+                    orders[index].restocked_at = Some(at);
+                    stock.backorders.pop_front();
+                }
+            }
+        }
+
+        while next < orders.len() && orders[next].date < day_end {
+            let order = &mut orders[next];
+            let id = order.product.id as usize;
+            let units = order.quantity as i64;
+            let stock = &mut stocks[id];
+            if matches!(order.status, OrderStatus::Cancelled) {
+                // Cancelled orders hold no stock
+            }
+            else if stock.backorders.is_empty() && stock.on_hand >= units {
+                stock.on_hand -= units;
+                sold[id] += units;
+            }
+            else {
+                stockout[id] = true;
+                if stock.rng.random_bool(BACKORDER_SHARE) {
+                    stock.backorders.push_back((next, units));
+                }
+                else {
+                    order.status = OrderStatus::Cancelled;
+                    order.cancel_reason = Some("OutOfStock".to_string());
+                }
+            }
+            next += 1;
+        }
+
+        for (id, stock) in stocks.iter_mut().enumerate() {
+            if stock.on_hand + stock.on_order() - stock.owed() <= stock.reorder_point() {
+                let mut lead = stock.policy.lead_days;
+                if stock.rng.random_bool(SUPPLIER_DELAY_RATE) {
+                    lead += stock.rng.random_range(5..=20);
+                }
+                // Suppliers deliver in the morning
+                let arrival = day + Duration::days(lead) + Duration::hours(6);
+                let units = stock.reorder_quantity() + stock.owed();
+                stock.inbound.push((arrival, units));
+            }
+            snapshots.push(InventorySnapshot {
+                date: day.date_naive(),
+                product_id: id as i64,
+                on_hand: stock.on_hand,
+                on_order: stock.on_order(),
+                received: received[id],
+                sold: sold[id],
+                backordered: stock.owed(),
+                stockout: stockout[id],
+            });
+        }
+        day = day_end;
+    }
+
+    // Backorders still waiting on a restock as of now keep that status
+    for stock in &stocks {
+        for (index, _) in &stock.backorders {
+            orders[*index].status = OrderStatus::Backordered;
+        }
+    }
+    for order in orders.iter_mut() {
+        if order.restocked_at.is_some_and(|at| at > now) {
+            order.status = OrderStatus::Backordered;
+        }
+    }
+    return snapshots;
+}
+
+/// Each product's policy is seeded by its id; it starts half way between its
+/// reorder point and a full restock
+fn new_stock(product_id: i64, daily_demand: f64) -> ProductStock {
+    let mut rng = StdRng::seed_from_u64(INVENTORY_SEED ^ product_id as u64);
+    let policy = StockPolicy {
+        lead_days: rng.random_range(3..=10),
+        safety_days: rng.random_range(1.0..5.0),
+        cover_days: rng.random_range(14.0..35.0),
+    };
+    let mut stock = ProductStock {
+        policy,
+        rng,
+        daily_demand,
+        on_hand: 0,
+        inbound: Vec::new(),
+        backorders: VecDeque::new(),
+    };
+    stock.on_hand = stock.reorder_point() + stock.reorder_quantity() / 2;
+    return stock;
+}
+
+// ------------------------ Static Data ------------------------
+
+const INVENTORY_SEED: u64 = 0x570C_4B1E;
+/// Chance a customer facing an empty shelf waits for the restock instead of cancelling
+const BACKORDER_SHARE: f64 = 0.6;
+/// Chance a restock arrives late
+const SUPPLIER_DELAY_RATE: f64 = 0.1;
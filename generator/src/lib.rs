@@ -0,0 +1,52 @@
+//! Synthetic e-commerce order data: customers, products, payments and
+//! orders, plus the shipments, returns, reviews, clickstream, fraud labels
+//! and inventory around them.
+//!
+//! `OrderGenerator` builds a `Dataset` in-process, and a `writer::Sink`
+//! (such as `writer::ParquetSink`) writes it out. Extra entity tables can
+//! be registered with `OrderGenerator::register`, for any type that is
+//! `Generate` and `Tabular`. A `spec::Spec` describes a whole dataset in
+//! a TOML file instead.
+//!
+//! Fallible calls return `error::Result`, whose `Error` says whether the
+//! config, the generation or the output went wrong.
+
+#![allow(clippy::needless_return)]
+
+pub mod authorization;
+pub mod cdc;
+pub mod clickstream;
+pub mod config;
+pub mod customers;
+pub mod dataset;
+pub mod discounts;
+pub mod error;
+pub mod expr;
+pub mod fraud;
+pub mod fx;
+pub mod integrity;
+pub mod inventory;
+pub mod manifest;
+pub mod orders;
+pub mod payments;
+mod pricing;
+pub mod products;
+pub mod registry;
+pub mod returns;
+pub mod reviews;
+pub mod shipments;
+pub mod spec;
+pub mod timing;
+mod utils;
+pub mod writer;
+
+pub use customers::Customer;
+pub use dataset::{Dataset, OrderGenerator};
+pub use error::{Error, Result};
+pub use manifest::Manifest;
+pub use orders::{Order, OrderStatus};
+pub use payments::Payment;
+pub use products::Product;
+pub use registry::{GenContext, Generate, Registry, Tabular};
+pub use spec::Spec;
+pub use writer::{ParquetSink, Sink};
@@ -1,43 +1,61 @@
-use std::env;
+#![allow(clippy::needless_return)]
 
-use rayon::prelude::*;
-use crate::orders::generate_order;
+use std::process;
 
-mod customers;
-mod payments;
-mod products;
-mod utils;
-mod orders;
-mod writer;
+use generator::config::{self, USAGE};
+use generator::cdc;
+use generator::writer::{ParquetSink, Sink};
+use generator::{Error, Manifest, OrderGenerator};
 
 fn main() {
-    // ----------------------- Parse input arg -----------------------
-    let args: Vec<String> = env::args().collect();
-    let num_orders: usize = if args.len() > 1 {
-        args[1].parse().unwrap_or_else(|_| {
-            eprintln!("Non-numeric argument passed for number of orders.");
-            std::process::exit(1);
-        })
-    } 
-    else { 2_000_000 };
-
-    // ----------------------- Generate in parallel -----------------------
-    println!("Generating {num_orders} orders...");
-    let orders: Vec<orders::Order> = {
-        (0..num_orders)
-    	    .into_par_iter()
-    	    .map(|_| generate_order())
-    	    .collect()
-	};
-
-    // ----------------------- Write to file -----------------------
-    let path = "../processor/data.parquet";
-	if let Err(err) = writer::write_parquet(&orders, path) {
-	    eprintln!("Failed to write Parquet file: {err}");
-	}
-	else {
-		return println!("Data generated successfully!");
-	}
+    if let Err(err) = run() {
+        eprintln!("{err}");
+        if let Error::Config(_) = err {
+            eprintln!("{USAGE}");
+        }
+        process::exit(err.exit_code());
+    }
 }
 
+fn run() -> generator::Result<()> {
+    // ----------------------- Parse input args -----------------------
+    let config = config::parse_args()?;
+
+    // ----------------------- Generate from a spec -----------------------
+    if let Some(spec) = &config.spec {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        println!("Generating {} tables from spec...", spec.tables.len());
+        let tables = spec.generate(config.num_orders, config.window(), seed)?;
+        let mut sink = ParquetSink::from_config(&config);
+        sink.write_tables(&tables)?;
+        Manifest::new(&config, seed, sink.written())?.write(&sink.output_dir)?;
+        println!("Data generated successfully!");
+        return Ok(());
+    }
+
+    // ----------------------- Generate -----------------------
+    println!("Generating {} orders...", config.num_orders);
+    let mut sink = ParquetSink::from_config(&config);
+    let cdc = config.cdc.map(|format| (format, config.cdc_retain));
+    let generator = OrderGenerator::from_config(config);
+    let dataset = generator.generate()?;
+    if dataset.fraud_incidents > 0 {
+        let planted = dataset.fraud_labels.as_ref().map_or(0, |labels| labels.len());
+        println!("Planted {} fraud incidents ({} orders).", dataset.fraud_incidents, planted);
+    }
 
+    // ----------------------- Write to file -----------------------
+    sink.write(&dataset)?;
+    let mut written = sink.written().to_vec();
+
+    // ----------------------- Capture changes -----------------------
+    // Only once the batch is on disk, so a failed write doesn't advance the CDC state past it
+    if let Some((format, retain)) = cdc {
+        written.extend(cdc::emit_changes(&dataset.orders, &dataset.returns, &sink.output_dir, format, retain)?);
+    }
+    let mut manifest = Manifest::new(generator.config(), dataset.seed, &written)?;
+    manifest.integrity = sink.integrity();
+    manifest.write(&sink.output_dir)?;
+    println!("Data generated successfully!");
+    return Ok(());
+}
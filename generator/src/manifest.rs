@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use arrow2::array::{Array, Int32Array, Int64Array, Utf8Array};
+use arrow2::datatypes::{DataType, TimeUnit};
+use arrow2::io::parquet::read;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::integrity::IntegrityCounts;
+use crate::utils;
+
+/// Earliest and latest
+type DateRange = (DateTime<Utc>, DateTime<Utc>);
+
+/// What a run wrote and how, saved as `manifest.json` in the output
+/// directory so downstream steps can check they have every file, unchanged,
+/// and see which settings produced them
+#[derive(Serialize)]
+pub struct Manifest<'a> {
+    pub generator_version: &'static str,
+    pub generated_at: DateTime<Utc>,
+    /// The seed the run's random choices were drawn from
+    pub seed: u64,
+    pub files: Vec<FileEntry>,
+    /// Orphaned keys and late dimension rows planted in a star schema
+    pub integrity: Option<IntegrityCounts>,
+    pub config: &'a Config,
+}
+
+/// One file as it ended up on disk
+#[derive(Debug, Serialize)]
+pub struct FileEntry {
+    /// Relative to the output directory
+    pub path: String,
+    /// Rows of a Parquet file or lines of an NDJSON file; `None` for other files
+    pub rows: Option<usize>,
+    pub bytes: u64,
+    pub sha256: String,
+    /// Earliest and latest date or timestamp in the file's top-level columns,
+    /// including timestamps stored as RFC 3339 strings
+    pub min_date: Option<DateTime<Utc>>,
+    pub max_date: Option<DateTime<Utc>>,
+}
+
+impl<'a> Manifest<'a> {
+    /// Describe `files`, hashing each and reading its Parquet footer. `seed` is the one the
+    /// run drew from.
+    pub fn new(config: &'a Config, seed: u64, files: &[PathBuf]) -> Result<Manifest<'a>> {
+        let files = files.iter().map(|path| describe(path, &config.output_dir)).collect::<Result<_>>()?;
+        return Ok(Manifest {
+            generator_version: env!("CARGO_PKG_VERSION"),
+            generated_at: Utc::now(),
+            seed,
+            files,
+            integrity: None,
+            config,
+        });
+    }
+
+    /// Write the manifest to `<output_dir>/manifest.json`
+    pub fn write(&self, output_dir: &str) -> Result<PathBuf> {
+        let path = Path::new(output_dir).join("manifest.json");
+        utils::write_atomic(&path, |writer| serde_json::to_writer_pretty(writer, self).map_err(io::Error::other))?;
+        return Ok(path);
+    }
+}
+
+fn describe(path: &Path, output_dir: &str) -> Result<FileEntry> {
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut File::open(path)?, &mut hasher)?;
+    let sha256 = hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect();
+    let (rows, range) = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => {
+            let (rows, range) = parquet_contents(path)?;
+            (Some(rows), range)
+        }
+        Some("ndjson") => (Some(BufReader::new(File::open(path)?).lines().count()), None),
+        _ => (None, None),
+    };
+    return Ok(FileEntry {
+        path: path.strip_prefix(output_dir).unwrap_or(path).to_string_lossy().into_owned(),
+        rows,
+        bytes,
+        sha256,
+        min_date: range.map(|(min, _)| min),
+        max_date: range.map(|(_, max)| max),
+    });
+}
+
+/// The row count, and the date range of the columns that hold dates. Only
+/// the footer is read: the range comes from the min/max statistics written
+/// with each column, which for RFC 3339 strings in UTC sort by time.
+fn parquet_contents(path: &Path) -> Result<(usize, Option<DateRange>)> {
+    let metadata = read::read_metadata(&mut File::open(path)?)?;
+    let schema = {
+        read::infer_schema(&metadata)?
+            .filter(|_, field| matches!(field.data_type(), DataType::Date32 | DataType::Timestamp(_, _) | DataType::Utf8))
+    };
+    let mut range = None;
+    for field in &schema.fields {
+        let statistics = read::statistics::deserialize(field, &metadata.row_groups)?;
+        for array in [&statistics.min_value, &statistics.max_value] {
+            for date in dates(array.as_ref()) {
+                range = Some(match range {
+                    Some((min, max)) => (date.min(min), date.max(max)),
+                    None => (date, date),
+                });
+            }
+        }
+    }
+    return Ok((metadata.num_rows, range));
+}
+
+/// The dates in a date, timestamp or string column. A string column only
+/// counts if its first value reads as a date or RFC 3339 timestamp.
+fn dates(array: &dyn Array) -> Vec<DateTime<Utc>> {
+    return match array.data_type() {
+        DataType::Date32 => {
+            let days = array.as_any().downcast_ref::<Int32Array>().unwrap();
+            days.iter().flatten().map(|&day| DateTime::UNIX_EPOCH + Duration::days(day as i64)).collect()
+        }
+        DataType::Timestamp(unit, _) => {
+            let values = array.as_any().downcast_ref::<Int64Array>().unwrap();
+            values.iter().flatten().filter_map(|&value| from_timestamp(value, *unit)).collect()
+        }
+        DataType::Utf8 => {
+            let strings = array.as_any().downcast_ref::<Utf8Array<i32>>().unwrap();
+            if strings.iter().flatten().next().and_then(parse_date).is_none() {
+                return Vec::new();
+            }
+            strings.iter().flatten().filter_map(parse_date).collect()
+        }
+        _ => Vec::new(),
+    };
+}
+
+fn from_timestamp(value: i64, unit: TimeUnit) -> Option<DateTime<Utc>> {
+    return match unit {
+        TimeUnit::Second => DateTime::from_timestamp(value, 0),
+        TimeUnit::Millisecond => DateTime::from_timestamp_millis(value),
+        TimeUnit::Microsecond => DateTime::from_timestamp_micros(value),
+        TimeUnit::Nanosecond => Some(DateTime::from_timestamp_nanos(value)),
+    };
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(text) {
+        return Some(timestamp.to_utc());
+    }
+    return NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().map(|date| date.and_time(NaiveTime::MIN).and_utc());
+}
@@ -1,11 +1,17 @@
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::collections::HashMap;
 use std::fmt;
 
-use crate::customers::{self, Customer, CustomerStatus};
-use crate::payments::{self, Payment};
+use crate::authorization::{self, AuthAttempt, DeclineCode};
+use crate::customers::{self, Customer, CustomerHistory, CustomerStatus, PurchaseHistory, StatusChange};
+use crate::discounts::DiscountPolicy;
+use crate::fx::{self, FxTable};
+use crate::payments::{InstrumentBook, Payment};
+use crate::pricing;
 use crate::products::{self, Product};
-use crate::utils::{generate_datetime, generate_uuid, round_decimal};
+use crate::timing::{self, TimeModel};
+use crate::utils::generate_uuid;
 use rand::Rng;
 
 #[derive(Debug, Serialize, Clone, Copy)]
@@ -15,6 +21,8 @@ pub enum OrderStatus {
     Completed,
     Refunded,
     Cancelled,
+    /// Waiting on a restock
+    Backordered,
 }
 
 impl fmt::Display for OrderStatus {
@@ -24,6 +32,7 @@ impl fmt::Display for OrderStatus {
             OrderStatus::Completed => "Completed",
             OrderStatus::Refunded => "Refunded",
             OrderStatus::Cancelled => "Cancelled",
+            OrderStatus::Backordered => "Backordered",
         };
         return write!(f, "{}", s);
     }
@@ -38,31 +47,53 @@ pub struct Order {
     pub payment: Payment,
     pub status: OrderStatus,
     pub discount: f64,
+    pub discount_reason: String,
     pub quantity: u32,
+    pub subtotal: f64,
+    pub discount_amount: f64,
+    pub tax: f64,
+    pub shipping: f64,
     pub total: f64,
+    pub currency: String,
+    pub total_local: f64,
+    pub total_usd: f64,
+    /// Every authorization attempt, oldest first; `payment` is the last one's
+    pub attempts: Vec<AuthAttempt>,
+    pub cancel_reason: Option<String>,
+    /// When a backordered order was filled by a restock
+    pub restocked_at: Option<DateTime<Utc>>,
+    /// The clickstream session the order was placed in
+    pub session_id: Option<String>,
+    /// Added to the chance the issuer flags an authorization attempt as fraud
+    #[serde(skip)]
+    pub fraud_risk: f64,
 }
 
 // --------------------------------------------
 
-fn get_status(id: &str, payment: &Payment, date: DateTime<Utc>) -> OrderStatus {
+/// Why an order is cancelled: a corrupted id, or the decline code of its
+/// last authorization attempt. Expired cards are reported as "BadCard".
+fn get_cancel_reason(id: &str, attempts: &[AuthAttempt]) -> Option<String> {
     if id.len() != 36 {
-        return OrderStatus::Cancelled;
+        return Some("BadId".to_string());
     }
-    let now = Utc::now();
-    if now.date_naive() > payment.details.expiration_date() {
+    let last = attempts.last()?;
+    return match last.decline_code? {
+        DeclineCode::Expired => Some("BadCard".to_string()),
+        code => Some(format!("{code:?}")),
+    };
+}
+
+fn get_status(cancel_reason: Option<&str>, date: DateTime<Utc>, now: DateTime<Utc>) -> OrderStatus {
+    if cancel_reason.is_some() {
         return OrderStatus::Cancelled;
     }
-    let month_ago = now - chrono::Duration::days(30);
-    let week_ago = now - chrono::Duration::days(7);
-    if date < month_ago { OrderStatus::Completed } 
-    else if date > month_ago && date < week_ago { OrderStatus::Refunded } 
-    else if date > week_ago && date <= now { OrderStatus::Completed } 
-    else if date > now { OrderStatus::Pending } 
+    // Refunds come from returns, once the order has shipped
+    if date > now { OrderStatus::Pending }
     else { OrderStatus::Completed }
 }
 
-fn get_quantity() -> u32 {
-    let mut rng = rand::rng();
+fn get_quantity(rng: &mut impl Rng) -> u32 {
     let temp = {
         rng.random_range(0..3) + 
         rng.random_range(0..2) - 
@@ -73,52 +104,173 @@ fn get_quantity() -> u32 {
     else { rng.random_range(1..=2) }
 }
 
-fn get_discount(customer: &Customer) -> f64 {
-    let mut rng = rand::rng();
-    match customer.status {
-        CustomerStatus::NewCustomer => 0.05,
-        CustomerStatus::ReturningCustomer => {
-            if rng.random_range(0..6) % 2 != 0 { return 0.03; } 
-            else { return 0.0; }
-        }
-        CustomerStatus::RewardsMember => 0.10,
-        CustomerStatus::Employee => 0.20,
-        CustomerStatus::Manager => 0.50,
-        CustomerStatus::Owner => 1.00,
-    }
+fn apply_discount(order: &mut Order, policy: &DiscountPolicy, rng: &mut impl Rng) {
+    let applied = policy.apply(&order.customer, &order.product, order.date, rng);
+    order.discount = applied.rate;
+    order.discount_reason = applied.reason;
 }
 
-fn compute_total(order: &mut Order) {
-    let raw = order.product.price * order.quantity as f64;
-    let discounted = raw * (1.0 - order.discount);
-    order.total = round_decimal(discounted);
+/// Price the order in USD, then convert the total into the customer's
+/// currency at the rate for the order date
+fn compute_total(order: &mut Order, fx: &FxTable) {
+    let amounts = pricing::price_order(&order.product, order.quantity, order.discount, &order.customer);
+    order.subtotal = amounts.subtotal;
+    order.discount_amount = amounts.discount_amount;
+    order.tax = amounts.tax;
+    order.shipping = amounts.shipping;
+    order.total = amounts.total;
+    order.currency = fx::currency_for_country(&order.customer.country).to_string();
+    order.total_usd = amounts.total;
+    order.total_local = fx.convert(amounts.total, &order.currency, order.date);
 }
 
 // --------------------------------------------
 
-pub fn generate_order() -> Order {
-    let mut id = generate_uuid();
-    let date = generate_datetime();
-    let product = products::generate_product();
-    let customer = customers::generate_customer();
-    let payment = payments::new_payment(&customer.name);
-    if rand::rng().random_range(0..1000) % 13 == 0 {
+/// Shared inputs for generating and re-pricing orders
+pub struct OrderContext<'a> {
+    /// Every customer as they start out, to draw from
+    pub customers: &'a [Customer],
+    /// With a customer history, orders carry the customer's attributes as of the order date
+    pub history: Option<&'a CustomerHistory>,
+    pub discounts: &'a DiscountPolicy,
+    pub rewards_threshold: f64,
+    pub fx: &'a FxTable,
+    pub instruments: &'a InstrumentBook,
+    pub time: &'a TimeModel,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// A random order in the period, as of `now`
+pub fn generate_order(ctx: &OrderContext, rng: &mut impl Rng, now: DateTime<Utc>) -> Order {
+    let mut id = generate_uuid(rng);
+    let mut customer = customers::generate_customer(ctx.customers, rng);
+    let date = ctx.time.sample(ctx.start, ctx.end, timing::utc_offset(&customer.timezone), rng);
+    if let Some(history) = ctx.history {
+        customer = history.customer_at(&customer, date).clone();
+    }
+    // Holiday products were checked by `Config::validate`
+    let product = match ctx.time.seasonal_product(date, rng) {
+        Some(id) => products::product_at(id as usize),
+        None => products::generate_product(rng),
+    };
+    let payment = ctx.instruments.new_payment(&customer, date, rng);
+    if rng.random_range(0..1000) % 13 == 0 {
         id.push('0'); // corrupt it slightly
     }
-    let mut order = Order {
-        id: id.clone(),
+    let quantity = get_quantity(rng);
+    let mut order = new_order(id, customer, product, quantity, date, payment);
+    settle(&mut order, ctx, 0.0, rng, now);
+    return order;
+}
+
+/// An order that is not yet priced or authorized; `settle` fills in the rest
+pub fn new_order(
+    id: String,
+    customer: Customer,
+    product: Product,
+    quantity: u32,
+    date: DateTime<Utc>,
+    payment: Payment,
+) -> Order {
+    return Order {
+        id,
         date,
         customer,
         product,
-        status: get_status(&id, &payment, date),
+        status: OrderStatus::Pending, // authorized in settle
         payment,
-        discount: 0.0, // filled in below
-        quantity: get_quantity(),
+        discount: 0.0,
+        discount_reason: String::new(),
+        quantity,
+        subtotal: 0.0,
+        discount_amount: 0.0,
+        tax: 0.0,
+        shipping: 0.0,
         total: 0.0,
+        currency: String::new(),
+        total_local: 0.0,
+        total_usd: 0.0,
+        attempts: Vec::new(),
+        cancel_reason: None,
+        restocked_at: None,
+        session_id: None,
+        fraud_risk: 0.0,
     };
-    order.discount = get_discount(&order.customer);
-    compute_total(&mut order);
-    return order;
+}
+
+/// Price the order, then authorize it, starting with its payment.
+/// `fraud_risk` raises the chance the issuer flags an attempt as fraud.
+pub fn settle(order: &mut Order, ctx: &OrderContext, fraud_risk: f64, rng: &mut impl Rng, now: DateTime<Utc>) {
+    order.fraud_risk = fraud_risk;
+    apply_discount(order, ctx.discounts, rng);
+    compute_total(order, ctx.fx);
+    authorize(order, ctx, rng, now);
+}
+
+/// Authorize the order's total, replacing any earlier attempts
+fn authorize(order: &mut Order, ctx: &OrderContext, rng: &mut impl Rng, now: DateTime<Utc>) {
+    let (attempts, payment) = {
+        authorization::authorize(
+            &order.customer,
+            order.payment.clone(),
+            order.date,
+            order.total,
+            order.fraud_risk,
+            ctx.instruments,
+            rng,
+        )
+    };
+    order.cancel_reason = get_cancel_reason(&order.id, &attempts);
+    order.status = get_status(order.cancel_reason.as_deref(), order.date, now);
+    order.attempts = attempts;
+    order.payment = payment;
+}
+
+/// Walk the orders in date order (sorting them in place), deriving each
+/// customer's status from their earlier orders and re-pricing the order with
+/// the discount that status earns. An order whose total changes is authorized
+/// again for the new total. Orders cancelled at checkout don't count towards
+/// the history; stock-outs and returns are taken after this pass, so they
+/// don't take back status already earned. Returns every status change.
+///
+/// History is per batch: every batch simulates its own period (by default
+/// the same one), so spend from an earlier batch isn't carried over.
+pub fn apply_purchase_history(
+    orders: &mut [Order],
+    ctx: &OrderContext,
+    now: DateTime<Utc>,
+    rng: &mut impl Rng,
+) -> Vec<StatusChange> {
+    orders.sort_by_key(|o| o.date);
+    let mut histories: HashMap<i64, PurchaseHistory> = HashMap::new();
+    let mut statuses: HashMap<i64, CustomerStatus> = HashMap::new();
+    let mut changes = Vec::new();
+
+    for order in orders.iter_mut() {
+        let history = histories.entry(order.customer.id).or_default();
+        let status = customers::status_from_history(&order.customer, history, ctx.rewards_threshold);
+        let previous = statuses.insert(order.customer.id, status);
+        if previous.is_some_and(|p| p != status) {
+            changes.push(StatusChange { customer_id: order.customer.id, date: order.date, status });
+        }
+        order.customer.status = status;
+        let authorized = order.total;
+        apply_discount(order, ctx.discounts, rng);
+        compute_total(order, ctx.fx);
+        if order.total != authorized {
+            authorize(order, ctx, rng, now);
+        }
+
+        match order.status {
+            OrderStatus::Cancelled => {}
+            _ => {
+                history.orders += 1;
+                history.spend += order.total;
+            }
+        }
+    }
+    return changes;
 }
 
 #[allow(dead_code)]
@@ -129,31 +281,52 @@ pub fn show(order: &Order) {
     println!("start~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~start");
     println!("Order Details:");
     println!(
-        "Order ID: {}\nDate: {}\nStatus: {}\nDiscount: {:.2}\nQuantity: {}\nTotal: {:.2}\n",
-        order.id, order.date, order.status, order.discount, order.quantity, order.total
+        "Order ID: {}\nDate: {}\nStatus: {}\nDiscount: {:.2} ({})\nQuantity: {}\n\
+         Subtotal: {:.2}\nDiscount Amount: {:.2}\nTax: {:.2}\nShipping: {:.2}\nTotal: {:.2} USD ({:.2} {})\n",
+        order.id, order.date, order.status, order.discount, order.discount_reason, order.quantity,
+        order.subtotal, order.discount_amount, order.tax, order.shipping, order.total,
+        order.total_local, order.currency
     );
     println!("Customer Details:");
     println!(
-        "Customer Id: {}\nName: {}\nEmail: {}\nAddress: {}\nStatus: {:?}\n",
+        "Customer Id: {}\nName: {}\nEmail: {}\nAddress: {}\nCountry: {}\nTime Zone: {}\nStatus: {:?}\n",
         order.customer.id,
         order.customer.name,
         order.customer.email,
         order.customer.address,
+        order.customer.country,
+        order.customer.timezone,
         order.customer.status,
     );
     println!("Payment Details:");
     println!(
-        "Transaction ID: {}\nExpiration Date: {}\n",
+        "Transaction ID: {}\nInstrument: {} ({} {:?} {})\nExpiration Date: {}\n",
         order.payment.transaction_id,
+        order.payment.instrument_id,
+        order.payment.details.method(),
+        order.payment.details.network(),
+        order.payment.details.masked_number(),
         order.payment.details.expiration_date()
     );
+    println!("Authorization Attempts:");
+    for attempt in &order.attempts {
+        println!(
+            "{} {} {} {:?}",
+            attempt.attempted_at, attempt.instrument_id, attempt.transaction_id, attempt.decline_code
+        );
+    }
+    if let Some(reason) = &order.cancel_reason {
+        println!("Cancel Reason: {}", reason);
+    }
+    println!();
     println!("Product Details:");
     println!(
-        "Product Id: {}\nName: {}\nCategory: {}\nPrice: {:.2}\n",
+        "Product Id: {}\nName: {}\nCategory: {}\nPrice: {:.2}\nWeight: {:.1} lb\n",
         order.product.id,
         order.product.name,
         order.product.category,
-        order.product.price
+        order.product.price,
+        order.product.weight
     );
     println!("end~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~end");
     thread::sleep(Duration::from_millis(10));
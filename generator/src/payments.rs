@@ -1,16 +1,40 @@
+use crate::customers::Customer;
 use crate::utils::generate_uuid;
-use chrono::NaiveDate;
-use rand::Rng;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum CardNetwork {
+    Visa,
+    Mastercard,
+    Amex,
+    Discover,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum WalletProvider {
+    ApplePay,
+    GooglePay,
+    PayPal,
+}
 
 #[derive(Debug, Serialize, Clone)]
 pub struct CardDetails {
+    /// PCI-style mask: BIN and last four visible, the full number is Luhn-valid
     pub number: String,
+    pub network: CardNetwork,
     pub expiration: NaiveDate,
 }
 
+/// A wallet token; the provider keeps it in sync with the funding card, so
+/// it always carries the card's current expiration.
 #[derive(Debug, Serialize, Clone)]
 pub struct Wallet {
+    pub provider: WalletProvider,
+    pub funding: CardDetails,
     pub expiration: NaiveDate,
 }
 
@@ -28,88 +52,341 @@ impl PaymentDetails {
             PaymentDetails::Wallet(wallet) => wallet.expiration,
         };
     }
+
+    pub fn method(&self) -> &'static str {
+        return match self {
+            PaymentDetails::Card(_) => "card",
+            PaymentDetails::Wallet(_) => "wallet",
+        };
+    }
+
+    pub fn network(&self) -> CardNetwork {
+        return match self {
+            PaymentDetails::Card(card) => card.network,
+            PaymentDetails::Wallet(wallet) => wallet.funding.network,
+        };
+    }
+
+    pub fn masked_number(&self) -> &str {
+        return match self {
+            PaymentDetails::Card(card) => &card.number,
+            PaymentDetails::Wallet(wallet) => &wallet.funding.number,
+        };
+    }
+
+    pub fn wallet_provider(&self) -> Option<WalletProvider> {
+        return match self {
+            PaymentDetails::Card(_) => None,
+            PaymentDetails::Wallet(wallet) => Some(wallet.provider),
+        };
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct Payment {
     pub details: PaymentDetails,
+    pub instrument_id: String,
     pub transaction_id: String,
 }
 
-fn parse_exp_date(exp_str: &str) -> NaiveDate {
-    let parts: Vec<u32> = {
-        exp_str
-            .split('/')
-            .map(|s| s.parse().unwrap_or(1))
-            .collect()
+// ------------------------ Instruments ------------------------
+
+/// One physical card; a renewal replaces it with the next version.
+#[derive(Debug, Clone)]
+pub struct CardVersion {
+    pub card: CardDetails,
+    pub issued: NaiveDate,
+}
+
+#[derive(Debug, Clone)]
+pub enum InstrumentKind {
+    /// Every version of the card, oldest first. The last version is never
+    /// renewed once `lapsed` is set.
+    Card { versions: Vec<CardVersion>, lapsed: bool },
+    /// Funded by the customer's card at `funding` (an index into their instruments)
+    Wallet { provider: WalletProvider, funding: usize, added: NaiveDate },
+}
+
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    pub id: String,
+    pub customer_id: i64,
+    pub kind: InstrumentKind,
+}
+
+/// Every customer's cards and wallets up to the end of the simulated period.
+/// Each customer's instruments are seeded by their id and dated from a fixed
+/// epoch, so batches agree on them whatever period they cover.
+pub struct InstrumentBook {
+    instruments: HashMap<i64, Vec<Instrument>>,
+}
+
+impl InstrumentBook {
+    pub fn simulate(customers: &[Customer], end: DateTime<Utc>) -> InstrumentBook {
+        let instruments = {
+            customers
+                .iter()
+                .map(|c| (c.id, simulate_instruments(c.id, end.date_naive())))
+                .collect()
+        };
+        return InstrumentBook { instruments };
+    }
+
+    pub fn instruments(&self) -> impl Iterator<Item = &Instrument> {
+        let mut ids: Vec<&i64> = self.instruments.keys().collect();
+        ids.sort();
+        return ids.into_iter().flat_map(|id| self.instruments[id].iter());
+    }
+
+    /// Pay for an order on `date`. Customers mostly use their first instrument
+    /// in good standing. Shortly after a renewal a merchant may still hold
+    /// the old card, and now and then a customer tries a lapsed card; both
+    /// present an expired card.
+    pub fn new_payment(&self, customer: &Customer, date: DateTime<Utc>, rng: &mut impl Rng) -> Payment {
+        let day = date.date_naive();
+        let instruments = &self.instruments[&customer.id];
+        let usable: Vec<usize> = (0..instruments.len()).filter(|i| is_usable(instruments, *i, day)).collect();
+
+        let index = {
+            if usable.is_empty() || rng.random_bool(LAPSED_RETRY_RATE) {
+                rng.random_range(0..instruments.len())
+            }
+            else if rng.random_bool(PREFERRED_SHARE) { usable[0] }
+            else { usable[rng.random_range(0..usable.len())] }
+        };
+        return payment_with(instruments, index, day, rng);
+    }
+
+    /// Pay again after a decline, preferring another instrument in good
+    /// standing. A customer with a single instrument just tries it again.
+    pub fn alternate_payment(
+        &self,
+        customer: &Customer,
+        date: DateTime<Utc>,
+        declined: &str,
+        rng: &mut impl Rng,
+    ) -> Payment {
+        let day = date.date_naive();
+        let instruments = &self.instruments[&customer.id];
+        let others: Vec<usize> = (0..instruments.len()).filter(|i| instruments[*i].id != declined).collect();
+        let usable: Vec<usize> = others.iter().copied().filter(|i| is_usable(instruments, *i, day)).collect();
+
+        let index = {
+            if !usable.is_empty() { usable[rng.random_range(0..usable.len())] }
+            else if !others.is_empty() { others[rng.random_range(0..others.len())] }
+            else { 0 }
+        };
+        return payment_with(instruments, index, day, rng);
+    }
+}
+
+/// Build a payment on the instrument at `index` with a fresh transaction id
+fn payment_with(instruments: &[Instrument], index: usize, day: NaiveDate, rng: &mut impl Rng) -> Payment {
+    let instrument = &instruments[index];
+    let details = match &instrument.kind {
+        InstrumentKind::Card { versions, .. } => PaymentDetails::Card(card_on_file(versions, day, rng)),
+        InstrumentKind::Wallet { provider, funding, .. } => {
+            let funding = match &instruments[*funding].kind {
+                InstrumentKind::Card { versions, .. } => current_version(versions, day).card.clone(),
+                InstrumentKind::Wallet { .. } => unreachable!("wallets are funded by cards"),
+            };
+            PaymentDetails::Wallet(Wallet {
+                provider: *provider,
+                expiration: funding.expiration,
+                funding,
+            })
+        }
+    };
+    return Payment {
+        details,
+        instrument_id: instrument.id.clone(),
+        transaction_id: generate_uuid(rng),
     };
-    let (month, year_suffix) = (parts[0], parts[1]);
-    let year = 2000 + year_suffix as i32;
+}
 
-    let first_of_next_month = if month == 12 {
-        NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap()
-    } 
-    else {
-        NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap()
+fn is_usable(instruments: &[Instrument], index: usize, day: NaiveDate) -> bool {
+    return match &instruments[index].kind {
+        InstrumentKind::Card { versions, .. } => {
+            versions[0].issued <= day && current_version(versions, day).card.expiration >= day
+        }
+        InstrumentKind::Wallet { funding, added, .. } => *added <= day && is_usable(instruments, *funding, day),
     };
-    return first_of_next_month.pred_opt().unwrap();
 }
 
-fn get_card_list() -> Vec<CardDetails> {
-    return vec![
-        ("************5171", "05/25"), ("************7690", "05/27"),
-        ("************2763", "06/24"), ("************3315", "04/26"),
-        ("************4072", "01/28"), ("************6869", "11/25"),
-        ("************2781", "08/26"), ("************0904", "09/25"),
-        ("************4469", "09/27"), ("************5164", "08/26"),
-        ("************3341", "04/25"), ("************1349", "07/26"),
-        ("************4167", "03/25"), ("************7168", "09/25"),
-        ("************8239", "10/26"), ("************0932", "11/26"),
-        ("************8186", "08/27"), ("************7668", "11/25"),
-        ("************9486", "09/27"), ("************1093", "02/27"),
-        ("************0238", "01/26"), ("************9611", "02/28"),
-        ("************7295", "09/26"), ("************8346", "08/27"),
-        ("************2464", "01/26"),
-    ].into_iter().map(|(n, d)| CardDetails {
-        number: n.to_string(),
-        expiration: parse_exp_date(d),
-    })
-    .collect();
-}
-
-fn get_wallet_list() -> Vec<Wallet> {
-    return {
-        get_card_list()
+fn current_version(versions: &[CardVersion], day: NaiveDate) -> &CardVersion {
+    return versions.iter().rev().find(|v| v.issued <= day).unwrap_or(&versions[0]);
+}
+
+/// The card details a merchant has on file: usually the current version,
+/// but for a while after a renewal sometimes the expired one.
+fn card_on_file(versions: &[CardVersion], day: NaiveDate, rng: &mut impl Rng) -> CardDetails {
+    let current = versions.iter().rposition(|v| v.issued <= day).unwrap_or(0);
+    if current > 0 {
+        let previous = &versions[current - 1].card;
+        let days_expired = (day - previous.expiration).num_days();
+        if (0..STALE_CARD_DAYS).contains(&days_expired) {
+            let stale_rate = STALE_CARD_RATE * (1.0 - days_expired as f64 / STALE_CARD_DAYS as f64);
+            if rng.random_bool(stale_rate) {
+                return previous.clone();
+            }
+        }
+    }
+    return versions[current].card.clone();
+}
+
+fn simulate_instruments(customer_id: i64, end: NaiveDate) -> Vec<Instrument> {
+    let mut rng = StdRng::seed_from_u64(PAYMENT_SEED ^ customer_id as u64);
+    let mut instruments = Vec::new();
+    // Where each card landed in `instruments`, and when it was first issued
+    let mut cards = Vec::new();
+
+    let card_count = rng.random_range(1..=3);
+    for n in 0..card_count {
+        // The first card predates the epoch; later ones are opened over the years after it
+        let issued = {
+            if n == 0 { INSTRUMENT_EPOCH - Duration::days(rng.random_range(30..365 * 4)) }
+            else { INSTRUMENT_EPOCH + Duration::days(rng.random_range(0..365 * 12)) }
+        };
+        let network = random_network(&mut rng);
+        let renewals = StdRng::seed_from_u64(rng.random());
+        // Every customer keeps their first card, even in a period before it was issued
+        if n > 0 && issued > end {
+            cards.push((None, issued));
+            continue;
+        }
+        cards.push((Some(instruments.len()), issued));
+        instruments.push(Instrument {
+            id: format!("pi_{customer_id}_{}", n + 1),
+            customer_id,
+            kind: card_versions(network, issued, end, renewals),
+        });
+    }
+
+    let providers = [WalletProvider::ApplePay, WalletProvider::GooglePay, WalletProvider::PayPal];
+    for (n, provider) in providers.into_iter().enumerate() {
+        if rng.random_bool(WALLET_ADOPTION_RATE) {
+            let (funding, issued) = cards[rng.random_range(0..card_count)];
+            let added = (INSTRUMENT_EPOCH + Duration::days(rng.random_range(-365..365 * 12))).max(issued);
+            let Some(funding) = funding.filter(|_| added <= end) else { continue };
+            instruments.push(Instrument {
+                id: format!("pi_{customer_id}_{}", card_count + n + 1),
+                customer_id,
+                kind: InstrumentKind::Wallet { provider, funding, added },
+            });
+        }
+    }
+    return instruments;
+}
+
+/// A card issued on `issued` and every renewal of it up to `end`. Renewals
+/// draw from their own `rng`, so a later `end` only adds versions.
+fn card_versions(network: CardNetwork, issued: NaiveDate, end: NaiveDate, mut rng: StdRng) -> InstrumentKind {
+    let mut versions = vec![CardVersion { card: new_card(network, issued, &mut rng), issued }];
+    let mut lapsed = false;
+    while versions.last().unwrap().card.expiration < end {
+        if rng.random_bool(LAPSE_RATE) {
+            lapsed = true;
+            break;
+        }
+        // Replacement cards arrive a few weeks before the old one expires
+        let previous = &versions.last().unwrap().card;
+        let issued = previous.expiration - Duration::days(rng.random_range(14..45));
+        let mut card = new_card(network, issued, &mut rng);
+        if rng.random_bool(SAME_NUMBER_RENEWAL_RATE) {
+            card.number = previous.number.clone();
+        }
+        versions.push(CardVersion { card, issued });
+    }
+    return InstrumentKind::Card { versions, lapsed };
+}
+
+fn random_network(rng: &mut impl Rng) -> CardNetwork {
+    return match rng.random_range(0..100) {
+        0..=51 => CardNetwork::Visa,
+        52..=81 => CardNetwork::Mastercard,
+        82..=92 => CardNetwork::Amex,
+        _ => CardNetwork::Discover,
+    };
+}
+
+/// A new card valid for three to five years, expiring at the end of its month
+fn new_card(network: CardNetwork, issued: NaiveDate, rng: &mut impl Rng) -> CardDetails {
+    let years = rng.random_range(3..=5);
+    let expiry_month = NaiveDate::from_ymd_opt(issued.year() + years, issued.month(), 1).unwrap();
+    return CardDetails {
+        number: mask(&luhn_number(network, rng)),
+        network,
+        expiration: last_day_of_month(expiry_month),
+    };
+}
+
+/// A random card number with the network's prefix and length and a valid
+/// Luhn check digit
+fn luhn_number(network: CardNetwork, rng: &mut impl Rng) -> String {
+    let (prefix, length) = match network {
+        CardNetwork::Visa => ("4".to_string(), 16),
+        CardNetwork::Mastercard => (rng.random_range(51..=55).to_string(), 16),
+        CardNetwork::Amex => (["34", "37"][rng.random_range(0..2)].to_string(), 15),
+        CardNetwork::Discover => ("6011".to_string(), 16),
+    };
+    let mut digits: Vec<u32> = prefix.chars().map(|c| c.to_digit(10).unwrap()).collect();
+    while digits.len() < length - 1 {
+        digits.push(rng.random_range(0..10));
+    }
+    digits.push(luhn_check_digit(&digits));
+    return digits.iter().map(|d| char::from_digit(*d, 10).unwrap()).collect();
+}
+
+fn luhn_check_digit(payload: &[u32]) -> u32 {
+    let sum: u32 = {
+        payload
             .iter()
-            .map(|card| Wallet { expiration: card.expiration })
-            .collect()
+            .rev()
+            .enumerate()
+            .map(|(i, d)| {
+                if i % 2 == 0 {
+                    let doubled = d * 2;
+                    if doubled > 9 { doubled - 9 } else { doubled }
+                }
+                else { *d }
+            })
+            .sum()
     };
+    return (10 - sum % 10) % 10;
 }
 
-pub fn choose_payment_method(name: &str) -> PaymentDetails {
-    let cards = get_card_list();
-    let wallets = get_wallet_list();
-    let name_to_index = [
-        "Ava Whitaker", "Liam Caldwell", "Isabella Greene", "Ethan Morrell",
-        "Maya Ellison", "Noah Blackwood", "Chloe Hartman", "Lucas Pennington",
-        "Sofia Langford", "Oliver Drayton", "Harper Linwood", "Sebastian Knox",
-        "Amelia Fairbanks", "Julian Royce", "Nora Halston", "Elijah Trent",
-        "Zoe Merrick", "Caleb Winslow", "Lily Hargrove", "Milo Carrington",
-        "Aria Templeton", "Declan Shore", "Vivian Leclair", "Grayson Holt",
-        "Clara Redmond",
-    ];
-    if let Some(index) = name_to_index.iter().position(|n| *n == name) {
-        let mut rng = rand::rng();
-        if rng.random_bool(0.5) { PaymentDetails::Card(cards[index].clone()) } 
-        else { PaymentDetails::Wallet(wallets[index].clone()) }
-    } 
-    else { panic!("Unknown customer name: {name}"); }
-}
-
-pub fn new_payment(name: &str) -> Payment {
-    return Payment {
-        details: choose_payment_method(name),
-        transaction_id: generate_uuid(),
+fn mask(number: &str) -> String {
+    let (bin, rest) = number.split_at(6);
+    let (hidden, last_four) = rest.split_at(rest.len() - 4);
+    return format!("{bin}{}{last_four}", "*".repeat(hidden.len()));
+}
+
+fn last_day_of_month(first: NaiveDate) -> NaiveDate {
+    let first_of_next_month = if first.month() == 12 {
+        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1).unwrap()
+    }
+    else {
+        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1).unwrap()
     };
+    return first_of_next_month.pred_opt().unwrap();
 }
 
+// ------------------------ Static Data ------------------------
+
+const PAYMENT_SEED: u64 = 0xCA2D_5EED;
+/// Instrument issue dates are drawn relative to this day, not the simulated period
+const INSTRUMENT_EPOCH: NaiveDate = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+/// Chance a card reaching its expiration is not renewed
+const LAPSE_RATE: f64 = 0.12;
+/// Chance a renewal keeps the card number (only the expiration changes)
+const SAME_NUMBER_RENEWAL_RATE: f64 = 0.7;
+/// Chance a customer adds each wallet provider
+const WALLET_ADOPTION_RATE: f64 = 0.35;
+/// Share of orders paid with the customer's preferred instrument
+const PREFERRED_SHARE: f64 = 0.6;
+/// Chance an order is attempted with any instrument, usable or not
+const LAPSED_RETRY_RATE: f64 = 0.01;
+/// Chance a merchant still has the previous card right after it expired;
+/// fades to zero over `STALE_CARD_DAYS`
+const STALE_CARD_RATE: f64 = 0.3;
+const STALE_CARD_DAYS: i64 = 90;
@@ -0,0 +1,94 @@
+use crate::customers::Customer;
+use crate::products::{Product, ProductCategory};
+use crate::utils::round_decimal;
+
+/// Every money component of an order, each rounded to cents.
+/// `total` is exactly `subtotal - discount_amount + tax + shipping`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrderAmounts {
+    pub subtotal: f64,
+    pub discount_amount: f64,
+    pub tax: f64,
+    pub shipping: f64,
+    pub total: f64,
+}
+
+/// Price `quantity` of `product` for `customer`, in USD. Sales tax (or VAT
+/// abroad) is charged on the discounted goods at the customer's rate;
+/// shipping isn't taxed and is free once the discounted goods reach the threshold.
+pub fn price_order(product: &Product, quantity: u32, discount: f64, customer: &Customer) -> OrderAmounts {
+    let subtotal = round_decimal(product.price * quantity as f64);
+    let discount_amount = round_decimal(subtotal * discount);
+    let taxable = subtotal - discount_amount;
+    let tax = round_decimal(taxable * tax_rate(customer));
+    let shipping = {
+        if taxable >= FREE_SHIPPING_THRESHOLD || taxable == 0.0 { 0.0 }
+        else { shipping_cost(product, quantity) }
+    };
+    return OrderAmounts {
+        subtotal,
+        discount_amount,
+        tax,
+        shipping,
+        total: round_decimal(taxable + tax + shipping),
+    };
+}
+
+/// VAT for customers abroad, otherwise the combined state and average local
+/// sales tax for the state in their address
+pub fn tax_rate(customer: &Customer) -> f64 {
+    if customer.country != "US" {
+        return {
+            VAT_RATES
+                .iter()
+                .find(|(code, _)| *code == customer.country)
+                .map(|(_, rate)| *rate)
+                .unwrap_or(0.0)
+        };
+    }
+    let state = state_code(&customer.address);
+    return {
+        STATE_TAX_RATES
+            .iter()
+            .find(|(code, _)| Some(*code) == state)
+            .map(|(_, rate)| *rate)
+            .unwrap_or(0.0)
+    };
+}
+
+/// Pull the two-letter state out of "street, city, ST 12345"
+pub fn state_code(address: &str) -> Option<&str> {
+    let (_, last) = address.rsplit_once(", ")?;
+    let (state, _zip) = last.split_once(' ')?;
+    if state.len() == 2 && state.chars().all(|c| c.is_ascii_uppercase()) { Some(state) }
+    else { None }
+}
+
+fn shipping_cost(product: &Product, quantity: u32) -> f64 {
+    let weight = product.weight * quantity as f64;
+    let (base, per_lb) = match product.category {
+        ProductCategory::Furniture => (39.99, 0.45),
+        ProductCategory::OutdoorEquipment | ProductCategory::Automotive => (9.99, 0.35),
+        ProductCategory::Grocery | ProductCategory::PetSupplies => (5.99, 0.20),
+        ProductCategory::Electronics => (6.99, 0.50),
+        _ => (4.99, 0.30),
+    };
+    return round_decimal(base + per_lb * weight);
+}
+
+// ------------------------ Static Data ------------------------
+
+const FREE_SHIPPING_THRESHOLD: f64 = 75.0;
+
+const STATE_TAX_RATES: [(&str, f64); 25] = [
+    ("AK", 0.0182), ("AZ", 0.0840), ("CA", 0.0885), ("CO", 0.0781), ("FL", 0.0700),
+    ("GA", 0.0742), ("IA", 0.0694), ("ID", 0.0603), ("IN", 0.0700), ("MN", 0.0804),
+    ("MO", 0.0840), ("NC", 0.0700), ("NE", 0.0697), ("NY", 0.0853), ("OH", 0.0724),
+    ("OR", 0.0000), ("SC", 0.0749), ("TN", 0.0955), ("TX", 0.0820), ("UT", 0.0735),
+    ("VA", 0.0577), ("VT", 0.0636), ("WA", 0.0938), ("WI", 0.0570), ("NV", 0.0824),
+];
+
+const VAT_RATES: [(&str, f64); 7] = [
+    ("CA", 0.13), ("GB", 0.20), ("DE", 0.19), ("FR", 0.20), ("JP", 0.10), ("MX", 0.16),
+    ("AU", 0.10),
+];
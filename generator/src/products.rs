@@ -1,9 +1,10 @@
 use rand::Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::error::{Error, Result};
 use crate::utils::round_decimal;
 
-#[derive(Debug, Serialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum ProductCategory {
     Grocery,
     HealthAndWellness,
@@ -47,53 +48,43 @@ impl std::fmt::Display for ProductCategory {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Product {
     pub id: i64,
     pub name: String,
     pub category: ProductCategory,
     pub price: f64,
+    /// Shipping weight in pounds
+    pub weight: f64,
 }
 
-pub fn generate_product() -> Product {
-    let id = rand::rng().random_range(0..50);
-    return Product {
-        id,
-        name: get_name(id),
-        category: get_category(id),
-        price: round_decimal(get_price(id)),
-    };
+pub fn generate_product(rng: &mut impl Rng) -> Product {
+    return product_at(rng.random_range(0..PRODUCT_COUNT));
 }
 
-fn get_name(id: i64) -> String {
-    return PRODUCT_IDS[id as usize].to_string();
+/// The product with `id`, for ids read from outside the generator
+pub fn product_by_id(id: i64) -> Result<Product> {
+    return {
+        usize::try_from(id)
+            .ok()
+            .filter(|&index| index < PRODUCT_COUNT)
+            .map(product_at)
+            .ok_or_else(|| Error::Generation(format!("Unknown product id: {id}")))
+    };
 }
 
-fn get_category(id: i64) -> ProductCategory {
-    return match id {
-        0..=3 => ProductCategory::Grocery,
-        4..=7 => ProductCategory::HealthAndWellness,
-        8..=10 => ProductCategory::CleaningSupplies,
-        11..=13 => ProductCategory::PetSupplies,
-        14..=16 => ProductCategory::OfficeSupplies,
-        17..=19 => ProductCategory::ToysAndGames,
-        20..=22 => ProductCategory::SeasonalItems,
-        23..=25 => ProductCategory::Clothing,
-        26..=28 => ProductCategory::Electronics,
-        29..=31 => ProductCategory::Furniture,
-        32..=34 => ProductCategory::Kitchenware,
-        35..=37 => ProductCategory::Lighting,
-        38..=40 => ProductCategory::BeddingAndBath,
-        41..=43 => ProductCategory::Tools,
-        44..=46 => ProductCategory::OutdoorEquipment,
-        47..=49 => ProductCategory::Automotive,
-        _ => panic!("Invalid product ID"),
+/// The product at `index`, which must be below `PRODUCT_COUNT`
+pub(crate) fn product_at(index: usize) -> Product {
+    return Product {
+        id: index as i64,
+        name: PRODUCT_IDS[index].to_string(),
+        category: PRODUCT_CATEGORIES[index],
+        price: round_decimal(PRODUCT_PRICES[index]),
+        weight: PRODUCT_WEIGHTS[index],
     };
 }
 
-fn get_price(id: i64) -> f64 {
-    return PRODUCT_PRICES[id as usize];
-}
+pub const PRODUCT_COUNT: usize = PRODUCT_IDS.len();
 
 const PRODUCT_IDS: [&str; 50] = [
     "Whole Wheat Bread", "Whole Milk (1 Gallon)", "Canned Black Beans", "White Rice (2 lb bag)",
@@ -114,6 +105,26 @@ const PRODUCT_IDS: [&str; 50] = [
     "Motor Oil (5W-30, 5qt)", "Windshield Wipers (front pair)", "Car Air Freshener -- Pine Scent",
 ];
 
+const PRODUCT_CATEGORIES: [ProductCategory; 50] = [
+    ProductCategory::Grocery, ProductCategory::Grocery, ProductCategory::Grocery, ProductCategory::Grocery,
+    ProductCategory::HealthAndWellness, ProductCategory::HealthAndWellness,
+    ProductCategory::HealthAndWellness, ProductCategory::HealthAndWellness,
+    ProductCategory::CleaningSupplies, ProductCategory::CleaningSupplies, ProductCategory::CleaningSupplies,
+    ProductCategory::PetSupplies, ProductCategory::PetSupplies, ProductCategory::PetSupplies,
+    ProductCategory::OfficeSupplies, ProductCategory::OfficeSupplies, ProductCategory::OfficeSupplies,
+    ProductCategory::ToysAndGames, ProductCategory::ToysAndGames, ProductCategory::ToysAndGames,
+    ProductCategory::SeasonalItems, ProductCategory::SeasonalItems, ProductCategory::SeasonalItems,
+    ProductCategory::Clothing, ProductCategory::Clothing, ProductCategory::Clothing,
+    ProductCategory::Electronics, ProductCategory::Electronics, ProductCategory::Electronics,
+    ProductCategory::Furniture, ProductCategory::Furniture, ProductCategory::Furniture,
+    ProductCategory::Kitchenware, ProductCategory::Kitchenware, ProductCategory::Kitchenware,
+    ProductCategory::Lighting, ProductCategory::Lighting, ProductCategory::Lighting,
+    ProductCategory::BeddingAndBath, ProductCategory::BeddingAndBath, ProductCategory::BeddingAndBath,
+    ProductCategory::Tools, ProductCategory::Tools, ProductCategory::Tools,
+    ProductCategory::OutdoorEquipment, ProductCategory::OutdoorEquipment, ProductCategory::OutdoorEquipment,
+    ProductCategory::Automotive, ProductCategory::Automotive, ProductCategory::Automotive,
+];
+
 const PRODUCT_PRICES: [f64; 50] = [
     3.99, 3.49, 0.89, 2.99, 3.99, 9.99, 3.00, 14.99, 4.99, 3.99,
     5.49, 30.00, 17.49, 12.99, 5.00, 3.49, 21.99, 16.79, 18.99, 9.89,
@@ -121,3 +132,11 @@ const PRODUCT_PRICES: [f64; 50] = [
     259.00, 189.99, 33.49, 39.99, 79.99, 90.00, 59.99, 9.99, 69.99, 44.99,
     35.00, 89.99, 29.99, 9.99, 119.99, 29.99, 45.00, 14.29, 23.49, 2.59,
 ];
+
+const PRODUCT_WEIGHTS: [f64; 50] = [
+    1.2, 8.8, 1.0, 2.1, 0.4, 0.5, 0.9, 0.2, 2.1, 2.3,
+    1.4, 15.6, 25.8, 1.1, 0.3, 0.7, 0.2, 3.5, 1.6, 0.6,
+    1.8, 1.3, 1.5, 0.5, 0.6, 0.9, 1.9, 0.2, 0.8, 32.0,
+    24.0, 38.5, 2.4, 3.1, 0.9, 12.0, 1.0, 0.3, 9.5, 7.2,
+    2.8, 4.6, 3.9, 0.6, 45.0, 11.0, 3.2, 10.5, 1.4, 0.1,
+];
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use arrow2::array::Array;
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::Schema;
+use chrono::{DateTime, Utc};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::config::Config;
+use crate::customers::{self, Customer};
+use crate::orders::{self, Order, OrderContext};
+use crate::payments::Payment;
+use crate::products::{self, Product};
+use crate::timing;
+
+/// What an entity generator can draw on. Each order gets its own, seeded
+/// from the run's seed.
+pub struct GenContext<'a> {
+    /// The built-in entities draw every random choice from this
+    pub rng: StdRng,
+    /// The moment the run treats as now
+    pub now: DateTime<Utc>,
+    pub config: &'a Config,
+    /// The simulated period, and the customer history, FX rates, instruments
+    /// and time model every order shares
+    pub orders: &'a OrderContext<'a>,
+}
+
+impl<'a> GenContext<'a> {
+    pub fn new(config: &'a Config, orders: &'a OrderContext<'a>, now: DateTime<Utc>, seed: u64) -> GenContext<'a> {
+        return GenContext { rng: StdRng::seed_from_u64(seed), now, config, orders };
+    }
+}
+
+/// An entity that can be generated on its own
+pub trait Generate: Sized {
+    fn generate(ctx: &mut GenContext) -> Self;
+}
+
+/// An entity with a fixed Arrow schema, so any list of them can be written as a table
+pub trait Tabular: Sized {
+    fn schema() -> Schema;
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>>;
+}
+
+/// A generated table, ready to write
+pub struct Table {
+    /// File name without the extension
+    pub name: String,
+    pub schema: Schema,
+    pub chunk: Chunk<Arc<dyn Array>>,
+}
+
+type Builder = Box<dyn Fn(&mut GenContext) -> Table + Send + Sync>;
+
+/// Extra entity tables generated with every run, next to the orders. Any
+/// type that is `Generate` and `Tabular` can be registered, including ones
+/// defined outside this crate.
+#[derive(Default)]
+pub struct Registry {
+    builders: Vec<Builder>,
+}
+
+impl Registry {
+    /// Generate `count` rows of `T` with every run, written as `name`
+    pub fn register<T: Generate + Tabular + 'static>(&mut self, name: &str, count: usize) {
+        let name = name.to_string();
+        self.builders.push(Box::new(move |ctx| {
+            let rows: Vec<T> = (0..count).map(|_| T::generate(ctx)).collect();
+            return Table { name: name.clone(), schema: T::schema(), chunk: T::to_chunk(&rows) };
+        }));
+    }
+
+    pub fn build(&self, ctx: &mut GenContext) -> Vec<Table> {
+        return self.builders.iter().map(|build| build(ctx)).collect();
+    }
+}
+
+// ------------------------ Built-in Entities ------------------------
+
+impl Generate for Customer {
+    fn generate(ctx: &mut GenContext) -> Self {
+        return customers::generate_customer(ctx.orders.customers, &mut ctx.rng);
+    }
+}
+
+impl Generate for Product {
+    fn generate(ctx: &mut GenContext) -> Self {
+        return products::generate_product(&mut ctx.rng);
+    }
+}
+
+/// A payment by a random customer at a random time in the period
+impl Generate for Payment {
+    fn generate(ctx: &mut GenContext) -> Self {
+        let customer = customers::generate_customer(ctx.orders.customers, &mut ctx.rng);
+        let time = ctx.orders.time;
+        let date = time.sample(ctx.orders.start, ctx.orders.end, timing::utc_offset(&customer.timezone), &mut ctx.rng);
+        return ctx.orders.instruments.new_payment(&customer, date, &mut ctx.rng);
+    }
+}
+
+impl Generate for Order {
+    fn generate(ctx: &mut GenContext) -> Self {
+        return orders::generate_order(ctx.orders, &mut ctx.rng, ctx.now);
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+
+use crate::orders::Order;
+use crate::products::ProductCategory;
+use crate::shipments::{DeliveryException, Shipment};
+use crate::utils::{generate_uuid, round_decimal};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnReason {
+    Defective,
+    WrongItem,
+    NotAsDescribed,
+    DidNotFit,
+    NoLongerNeeded,
+    /// The package arrived damaged
+    Damaged,
+    /// The package never arrived; refunded without a return
+    LostInTransit,
+}
+
+impl ReturnReason {
+    /// Whether the store is at fault, so the customer gets shipping back and pays no fee
+    fn store_at_fault(&self) -> bool {
+        return !matches!(self, ReturnReason::DidNotFit | ReturnReason::NoLongerNeeded);
+    }
+}
+
+/// A return (or refund without one) against a single shipment
+#[derive(Debug, Serialize, Clone)]
+pub struct Return {
+    pub return_id: String,
+    pub order_id: String,
+    pub shipment_id: String,
+    pub reason: ReturnReason,
+    pub quantity: u32,
+    pub requested_at: DateTime<Utc>,
+    /// `None` until the refund is issued
+    pub refunded_at: Option<DateTime<Utc>>,
+    /// When the refund is issued, which may be after now
+    #[serde(skip)]
+    pub refund_due: DateTime<Utc>,
+    /// USD, after any restocking fee
+    pub refund_amount: f64,
+    pub restocking_fee: f64,
+    /// Every unit of the order came back and the whole total was refunded
+    pub full_refund: bool,
+}
+
+/// Returns for an order's shipments as of now. Damaged packages usually
+/// come back and lost ones are refunded; otherwise the chance of a return
+/// depends on the product's category.
+pub fn plan_returns(order: &Order, shipments: &[Shipment], rng: &mut impl Rng, now: DateTime<Utc>) -> Vec<Return> {
+    let mut returns = Vec::new();
+    for shipment in shipments {
+        let (reason, requested_at) = match (shipment.exception, shipment.delivered_at) {
+            (Some(DeliveryException::Lost), _) => {
+                (ReturnReason::LostInTransit, shipment.promised_at + Duration::days(rng.random_range(3..10)))
+            }
+            (Some(DeliveryException::Damaged), Some(delivered)) if rng.random_bool(DAMAGED_RETURN_RATE) => {
+                (ReturnReason::Damaged, delivered + Duration::hours(rng.random_range(2..72)))
+            }
+            (_, Some(delivered)) if rng.random_bool(return_rate(order.product.category)) => {
+                (pick_reason(order.product.category, rng), delivered + Duration::days(rng.random_range(1..30)))
+            }
+            _ => continue,
+        };
+        if requested_at > now {
+            continue;
+        }
+
+        let quantity = {
+            if reason.store_at_fault() || shipment.quantity == 1 { shipment.quantity }
+            else { rng.random_range(1..=shipment.quantity) }
+        };
+        let share = quantity as f64 / order.quantity as f64;
+        let (refund_amount, restocking_fee) = {
+            if reason.store_at_fault() { (round_decimal(order.total * share), 0.0) }
+            else {
+                let goods = (order.total - order.shipping) * share;
+                let fee = round_decimal(goods * restocking_fee_rate(order.product.category));
+                (round_decimal(goods - fee), fee)
+            }
+        };
+        let refund_due = requested_at + Duration::days(rng.random_range(3..10));
+        returns.push(Return {
+            return_id: generate_uuid(rng),
+            order_id: order.id.clone(),
+            shipment_id: shipment.shipment_id.clone(),
+            reason,
+            quantity,
+            requested_at,
+            refunded_at: if refund_due <= now { Some(refund_due) } else { None },
+            refund_due,
+            refund_amount,
+            restocking_fee,
+            full_refund: quantity == order.quantity && refund_amount == order.total,
+        });
+    }
+    return returns;
+}
+
+/// Chance a delivered package of this category comes back
+pub fn return_rate(category: ProductCategory) -> f64 {
+    return match category {
+        ProductCategory::Clothing => 0.2,
+        ProductCategory::Electronics => 0.1,
+        ProductCategory::Furniture => 0.08,
+        ProductCategory::ToysAndGames | ProductCategory::BeddingAndBath => 0.06,
+        ProductCategory::Kitchenware | ProductCategory::Lighting | ProductCategory::OutdoorEquipment => 0.05,
+        ProductCategory::Tools | ProductCategory::SeasonalItems => 0.04,
+        ProductCategory::Automotive | ProductCategory::PetSupplies => 0.03,
+        ProductCategory::OfficeSupplies | ProductCategory::HealthAndWellness => 0.02,
+        ProductCategory::Grocery | ProductCategory::CleaningSupplies => 0.01,
+    };
+}
+
+/// Fee kept on buyer's-remorse returns of goods that are hard to resell
+fn restocking_fee_rate(category: ProductCategory) -> f64 {
+    return match category {
+        ProductCategory::Electronics | ProductCategory::Furniture => 0.15,
+        ProductCategory::Tools | ProductCategory::OutdoorEquipment => 0.1,
+        _ => 0.0,
+    };
+}
+
+fn pick_reason(category: ProductCategory, rng: &mut impl Rng) -> ReturnReason {
+    if category == ProductCategory::Clothing && rng.random_bool(0.55) {
+        return ReturnReason::DidNotFit;
+    }
+    return match rng.random_range(0..100) {
+        0..=29 => ReturnReason::Defective,
+        30..=44 => ReturnReason::WrongItem,
+        45..=64 => ReturnReason::NotAsDescribed,
+        _ => ReturnReason::NoLongerNeeded,
+    };
+}
+
+// ------------------------ Static Data ------------------------
+
+/// Chance a customer sends back an order that arrived damaged
+const DAMAGED_RETURN_RATE: f64 = 0.8;
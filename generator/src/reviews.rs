@@ -0,0 +1,172 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+
+use crate::orders::Order;
+use crate::returns::{self, Return, ReturnReason};
+use crate::shipments::Shipment;
+use crate::utils::generate_uuid;
+
+/// A customer's review of the product they bought
+#[derive(Debug, Serialize, Clone)]
+pub struct Review {
+    pub review_id: String,
+    pub order_id: String,
+    pub product_id: i64,
+    pub customer_id: i64,
+    /// 1 to 5 stars
+    pub rating: u8,
+    pub title: String,
+    pub body: String,
+    pub reviewed_at: DateTime<Utc>,
+    pub helpful_votes: u32,
+}
+
+/// Maybe review a delivered order. Customers who sent it back are likelier
+/// to write one and rate it low; otherwise ratings drift lower for the
+/// categories that get returned most.
+pub fn plan_review(
+    order: &Order,
+    shipments: &[Shipment],
+    returns: &[Return],
+    rng: &mut impl Rng,
+    now: DateTime<Utc>,
+) -> Option<Review> {
+    let delivered = shipments.iter().filter_map(|s| s.delivered_at).max()?;
+    let returned = returns.first().map(|r| r.reason);
+    let review_rate = if returned.is_some() { RETURNED_REVIEW_RATE } else { REVIEW_RATE };
+    if !rng.random_bool(review_rate) {
+        return None;
+    }
+    let reviewed_at = delivered + Duration::hours(rng.random_range(6..24 * 21));
+    if reviewed_at > now {
+        return None;
+    }
+
+    let rating = match returned {
+        Some(ReturnReason::Damaged | ReturnReason::Defective | ReturnReason::WrongItem) => rng.random_range(1..=2),
+        Some(ReturnReason::NotAsDescribed) => rng.random_range(1..=3),
+        Some(_) => rng.random_range(2..=4),
+        None => {
+            // Five stars is most common; unhappy keepers are spread below
+            let roll: f64 = rng.random();
+            let low = LOW_RATING_SHARE * (1.0 + 5.0 * returns::return_rate(order.product.category));
+            if roll < low { rng.random_range(1..=3) }
+            else if roll < low + 0.3 { 4 }
+            else { 5 }
+        }
+    };
+    let name = &order.product.name;
+    let title = {
+        let options = TITLES[rating as usize - 1];
+        options[rng.random_range(0..options.len())].to_string()
+    };
+    let mut sentences = vec![OPENINGS[rating as usize - 1][rng.random_range(0..3)].replace("{product}", name)];
+    if let Some(reason) = returned {
+        sentences.push(return_sentence(reason).to_string());
+    }
+    // Most reviews are a line or two; a few ramble
+    let extra = if rng.random_bool(0.15) { rng.random_range(4..12) } else { rng.random_range(0..3) };
+    let pool = if rating >= 4 { POSITIVE_DETAILS } else { NEGATIVE_DETAILS };
+    let category = order.product.category.to_string().to_lowercase();
+    for _ in 0..extra {
+        sentences.push(pool[rng.random_range(0..pool.len())].replace("{category}", &category));
+    }
+
+    return Some(Review {
+        review_id: generate_uuid(rng),
+        order_id: order.id.clone(),
+        product_id: order.product.id,
+        customer_id: order.customer.id,
+        rating,
+        title,
+        body: sentences.join(" "),
+        reviewed_at,
+        helpful_votes: if rng.random_bool(0.3) { rng.random_range(1..50) } else { 0 },
+    });
+}
+
+fn return_sentence(reason: ReturnReason) -> &'static str {
+    return match reason {
+        ReturnReason::Defective => "It stopped working almost right away, so it went back.",
+        ReturnReason::WrongItem => "I was sent the wrong item and had to return it.",
+        ReturnReason::NotAsDescribed => "It looked nothing like the listing, so I returned it.",
+        ReturnReason::DidNotFit => "The sizing was off and it didn't fit, so I sent it back.",
+        ReturnReason::NoLongerNeeded => "Ended up not needing it and returned it.",
+        ReturnReason::Damaged => "It showed up damaged and I had to send it back.",
+        ReturnReason::LostInTransit => "The package never arrived, but the refund was quick.",
+    };
+}
+
+// ------------------------ Static Data ------------------------
+
+/// Share of delivered orders that get a review
+const REVIEW_RATE: f64 = 0.2;
+const RETURNED_REVIEW_RATE: f64 = 0.45;
+/// Share of 1-3 star ratings on kept orders before the category adjustment
+const LOW_RATING_SHARE: f64 = 0.08;
+
+/// Titles by rating, one to five stars
+const TITLES: [&[&str]; 5] = [
+    &["Terrible", "Do not buy", "Waste of money", "Very disappointed"],
+    &["Not great", "Wouldn't buy again", "Below expectations"],
+    &["It's okay", "Average", "Does the job, barely", "Mixed feelings"],
+    &["Pretty good", "Solid purchase", "Happy with it", "Good value"],
+    &["Love it!", "Excellent", "Exactly what I needed", "Five stars", "Highly recommend"],
+];
+
+/// Opening sentences by rating; `{product}` is replaced with the product name
+const OPENINGS: [[&str; 3]; 5] = [
+    [
+        "The {product} was a complete letdown.",
+        "I regret buying the {product}.",
+        "Save yourself the trouble and skip the {product}.",
+    ],
+    [
+        "The {product} didn't live up to the description.",
+        "I expected more from the {product}.",
+        "The {product} has some real problems.",
+    ],
+    [
+        "The {product} is fine, nothing special.",
+        "The {product} works, but I have a few complaints.",
+        "Mixed experience with the {product}.",
+    ],
+    [
+        "The {product} is a good buy for the price.",
+        "Pretty happy with the {product} overall.",
+        "The {product} does what it says.",
+    ],
+    [
+        "The {product} is fantastic.",
+        "Couldn't be happier with the {product}.",
+        "The {product} exceeded my expectations.",
+    ],
+];
+
+/// `{category}` is replaced with the product category
+const POSITIVE_DETAILS: [&str; 10] = [
+    "Shipping was quick and the packaging was solid.",
+    "Quality feels better than other {category} I've bought.",
+    "I've been using it daily for a couple of weeks now with no issues.",
+    "Easy to set up and simple to use.",
+    "Great value compared to what the big brands charge.",
+    "My whole family has been using it.",
+    "I already ordered a second one as a gift.",
+    "It matches the photos exactly.",
+    "Customer service answered my question within a day.",
+    "Would definitely buy from this store again.",
+];
+
+const NEGATIVE_DETAILS: [&str; 10] = [
+    "The materials feel cheap.",
+    "It took much longer to arrive than promised.",
+    "Instructions were confusing and parts of it were missing.",
+    "I've had better {category} for half the price.",
+    "It broke after a few uses.",
+    "The photos online are misleading.",
+    "Customer service was slow to respond.",
+    "The packaging was torn when it arrived.",
+    "It's smaller than I expected.",
+    "I wouldn't recommend it to a friend.",
+];
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::Serialize;
+
+use crate::orders::{Order, OrderStatus};
+use crate::timing::utc_offset;
+use crate::utils::generate_uuid;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Carrier {
+    Ups,
+    FedEx,
+    Usps,
+    Dhl,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceLevel {
+    Ground,
+    Express,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryException {
+    /// Held up in the carrier network; arrives late
+    Delayed,
+    /// The carrier couldn't deliver to the address at first; arrives late
+    AddressIssue,
+    /// Delivered, but arrived damaged
+    Damaged,
+    /// Never delivered
+    Lost,
+}
+
+/// One package sent for an order. Most orders ship in a single package;
+/// some are split across two warehouses.
+#[derive(Debug, Serialize, Clone)]
+pub struct Shipment {
+    pub shipment_id: String,
+    pub order_id: String,
+    pub carrier: Carrier,
+    pub service: ServiceLevel,
+    pub tracking_number: String,
+    pub warehouse: &'static str,
+    pub quantity: u32,
+    pub shipped_at: DateTime<Utc>,
+    /// Delivery date promised at checkout, for SLA reporting
+    pub promised_at: DateTime<Utc>,
+    /// `None` while in transit, or if the package was lost
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub exception: Option<DeliveryException>,
+}
+
+/// Packages for an order that has shipped by now. Completed orders ship
+/// once they're placed (or restocked); nothing else ships.
+pub fn plan_shipments(order: &Order, rng: &mut impl Rng, now: DateTime<Utc>) -> Vec<Shipment> {
+    if !matches!(order.status, OrderStatus::Completed) {
+        return Vec::new();
+    }
+    let ready = order.restocked_at.unwrap_or(order.date);
+    let service = {
+        if rng.random_bool(EXPRESS_SHARE) { ServiceLevel::Express }
+        else { ServiceLevel::Ground }
+    };
+    let warehouses = nearest_warehouses(order);
+
+    let split = order.quantity >= 2 && rng.random_bool(SPLIT_SHIPMENT_RATE);
+    let parts = {
+        if split {
+            let first = rng.random_range(1..order.quantity);
+            vec![(warehouses[0], first), (warehouses[1], order.quantity - first)]
+        }
+        else { vec![(warehouses[0], order.quantity)] }
+    };
+
+    let mut shipments = Vec::with_capacity(parts.len());
+    for (i, ((warehouse, offset), quantity)) in parts.into_iter().enumerate() {
+        let mut shipped_at = ready + Duration::hours(rng.random_range(4..48));
+        if i > 0 {
+            // The rest of a split order waits a few days on the other warehouse
+            shipped_at += Duration::days(rng.random_range(1..5));
+        }
+        if shipped_at > now {
+            continue;
+        }
+        let international = order.customer.country != "US";
+        let carrier = pick_carrier(international, rng);
+        let zones = (utc_offset(&order.customer.timezone) - offset).unsigned_abs() as i64;
+        let standard_days = if international { INTERNATIONAL_TRANSIT_DAYS } else { 2 + zones };
+        let promised_days = match service {
+            ServiceLevel::Ground => standard_days + 1,
+            ServiceLevel::Express => (standard_days / 2).max(1),
+        };
+        let mut transit = {
+            Duration::days(promised_days - rng.random_range(0..=1)) + Duration::hours(rng.random_range(-6..6))
+        };
+
+        let exception = pick_exception(rng);
+        match exception {
+            Some(DeliveryException::Delayed) => transit += Duration::days(rng.random_range(2..8)),
+            Some(DeliveryException::AddressIssue) => transit += Duration::days(rng.random_range(1..4)),
+            _ => {}
+        }
+        let delivered_at = shipped_at + transit;
+        shipments.push(Shipment {
+            shipment_id: generate_uuid(rng),
+            order_id: order.id.clone(),
+            carrier,
+            service,
+            tracking_number: tracking_number(carrier, rng),
+            warehouse,
+            quantity,
+            shipped_at,
+            promised_at: shipped_at + Duration::days(promised_days),
+            delivered_at: {
+                if exception == Some(DeliveryException::Lost) || delivered_at > now { None }
+                else { Some(delivered_at) }
+            },
+            exception,
+        });
+    }
+    return shipments;
+}
+
+/// Warehouses ordered by how many time zones separate them from the
+/// customer; international orders leave from the warehouse nearest their port.
+fn nearest_warehouses(order: &Order) -> Vec<(&'static str, i32)> {
+    let offset = match order.customer.country.as_str() {
+        "US" => utc_offset(&order.customer.timezone),
+        "MX" => -6,
+        "JP" | "AU" => -8,
+        _ => -5,
+    };
+    let mut warehouses: Vec<(&'static str, i32)> = WAREHOUSES.to_vec();
+    warehouses.sort_by_key(|(_, w)| (offset - w).abs());
+    return warehouses;
+}
+
+fn pick_carrier(international: bool, rng: &mut impl Rng) -> Carrier {
+    let roll = rng.random_range(0..100);
+    if international {
+        return if roll < 70 { Carrier::Dhl } else { Carrier::FedEx };
+    }
+    return match roll {
+        0..=39 => Carrier::Ups,
+        40..=69 => Carrier::FedEx,
+        _ => Carrier::Usps,
+    };
+}
+
+fn pick_exception(rng: &mut impl Rng) -> Option<DeliveryException> {
+    return match rng.random_range(0..1000) {
+        0..=29 => Some(DeliveryException::Delayed),
+        30..=39 => Some(DeliveryException::AddressIssue),
+        40..=49 => Some(DeliveryException::Damaged),
+        50..=54 => Some(DeliveryException::Lost),
+        _ => None,
+    };
+}
+
+/// A tracking number in the carrier's usual shape
+fn tracking_number(carrier: Carrier, rng: &mut impl Rng) -> String {
+    return match carrier {
+        Carrier::Ups => format!("1Z{}", digits(16, rng)),
+        Carrier::FedEx => digits(12, rng),
+        Carrier::Usps => format!("94{}", digits(20, rng)),
+        Carrier::Dhl => digits(10, rng),
+    };
+}
+
+fn digits(count: usize, rng: &mut impl Rng) -> String {
+    return (0..count).map(|_| char::from(b'0' + rng.random_range(0..10))).collect();
+}
+
+// ------------------------ Static Data ------------------------
+
+/// (warehouse, standard UTC offset of its location)
+const WAREHOUSES: [(&str, i32); 3] = [
+    ("Reno, NV", -8),
+    ("Dallas, TX", -6),
+    ("Columbus, OH", -5),
+];
+
+const EXPRESS_SHARE: f64 = 0.15;
+const SPLIT_SHIPMENT_RATE: f64 = 0.12;
+const INTERNATIONAL_TRANSIT_DAYS: i64 = 8;
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use arrow2::array::{Array, BooleanArray, Float64Array, Int32Array, Int64Array, UInt32Array, Utf8Array};
+use arrow2::chunk::Chunk;
+use arrow2::compute::cast::{CastOptions, cast};
+use arrow2::compute::take::take;
+use arrow2::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow2::bitmap::Bitmap;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand::distr::weighted::WeightedIndex;
+use rand::prelude::Distribution;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::expr::{Expr, ExprType};
+use crate::registry::Table;
+use crate::utils::generate_uuid;
+
+/// A dataset described in a TOML file instead of Rust: its tables, their
+/// columns, and how each column is generated. See `default_spec.toml`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Spec {
+    /// In dependency order once loaded, so referenced tables come first
+    #[serde(rename = "table")]
+    pub tables: Vec<TableSpec>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TableSpec {
+    pub name: String,
+    /// `None` takes the row count given on the command line
+    #[serde(default)]
+    pub rows: Option<usize>,
+    #[serde(rename = "column")]
+    pub columns: Vec<ColumnSpec>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ColumnSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ColumnType,
+    /// Share of rows left null
+    #[serde(default)]
+    pub null_rate: f64,
+    #[serde(flatten)]
+    pub generator: ColumnGenerator,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Int,
+    Float,
+    String,
+    Bool,
+    Date,
+    Timestamp,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "generator", rename_all = "snake_case")]
+pub enum ColumnGenerator {
+    /// `start`, `start + step`, ...
+    Sequence {
+        #[serde(default = "one")]
+        start: i64,
+        #[serde(default = "one")]
+        step: i64,
+    },
+    Uuid,
+    /// A random value, uniformly unless weighted
+    Choice {
+        values: Vec<Literal>,
+        #[serde(default)]
+        weights: Option<Vec<f64>>,
+    },
+    /// The values in order, wrapping around
+    Cycle { values: Vec<Literal> },
+    /// Uniform over `[min, max]`; dates and timestamps default to the simulated period
+    Range {
+        #[serde(default)]
+        min: Option<Literal>,
+        #[serde(default)]
+        max: Option<Literal>,
+    },
+    /// The column's value in a random row of another table. References to
+    /// the same table within a row share that row unless their `relation`s differ.
+    Reference {
+        table: String,
+        column: String,
+        #[serde(default)]
+        relation: Option<String>,
+    },
+    /// Derived from the columns before it in the same table, e.g. `round(price * quantity, 2)`
+    Expression { expr: Expr },
+}
+
+/// A value written in the spec. Dates and timestamps are written as strings.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum Literal {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl ColumnType {
+    fn expr_type(self) -> ExprType {
+        return match self {
+            ColumnType::Int => ExprType::Int,
+            ColumnType::Float => ExprType::Float,
+            ColumnType::String => ExprType::String,
+            ColumnType::Bool => ExprType::Bool,
+            ColumnType::Date => ExprType::Date,
+            ColumnType::Timestamp => ExprType::Timestamp,
+        };
+    }
+}
+
+fn one() -> i64 {
+    return 1;
+}
+
+impl Spec {
+    /// The customer, product and order model, in spec form
+    pub fn builtin() -> Spec {
+        return Spec::parse(DEFAULT_SPEC).expect("The built-in spec is invalid");
+    }
+
+    pub fn load(path: &str) -> io::Result<Spec> {
+        return Spec::parse(&fs::read_to_string(path)?);
+    }
+
+    /// Parse and check a spec, ordering its tables so each comes after the tables it references
+    pub fn parse(text: &str) -> io::Result<Spec> {
+        let spec: Spec = toml::from_str(text).map_err(io::Error::other)?;
+        let mut pending = spec.tables;
+        let mut tables: Vec<TableSpec> = Vec::new();
+        while !pending.is_empty() {
+            let ready = {
+                pending
+                    .iter()
+                    .position(|t| t.references().all(|name| name == t.name || tables.iter().any(|done| done.name == name)))
+            };
+            let Some(index) = ready
+            else {
+                let names: Vec<&str> = pending.iter().map(|t| t.name.as_str()).collect();
+                return Err(invalid(format!("Tables reference each other in a cycle or reference unknown tables: {}", names.join(", "))));
+            };
+            let table = pending.remove(index);
+            table.check(&tables)?;
+            tables.push(table);
+        }
+        return Ok(Spec { tables });
+    }
+
+    /// Generate every table, in order. `default_rows` sizes tables without a
+    /// row count, and `window` bounds dates and timestamps without a range.
+    /// The same seed and window generate the same tables. Fails if a range
+    /// with only one bound ends up empty against `window`, or a reference
+    /// points at a table that ends up with no rows.
+    pub fn generate(&self, default_rows: usize, window: (DateTime<Utc>, DateTime<Utc>), seed: u64) -> crate::error::Result<Vec<Table>> {
+        for table in &self.tables {
+            let rows = table.rows.unwrap_or(default_rows);
+            for column in &table.columns {
+                match &column.generator {
+                    ColumnGenerator::Range { min, max } if matches!(column.kind, ColumnType::Date | ColumnType::Timestamp) => {
+                        let (start, end) = time_bounds(min, max, window);
+                        if start > end {
+                            let msg = format!("{}.{}: the range ends before it starts in the simulated period", table.name, column.name);
+                            return Err(Error::Config(msg));
+                        }
+                    }
+                    ColumnGenerator::Reference { table: target, .. } if rows > 0 => {
+                        let target_rows = self.tables.iter().find(|t| &t.name == target).and_then(|t| t.rows).unwrap_or(default_rows);
+                        if target_rows == 0 {
+                            let msg = format!("{}.{}: {target} has no rows to reference", table.name, column.name);
+                            return Err(Error::Generation(msg));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut tables: Vec<Table> = Vec::new();
+        for table in &self.tables {
+            let generated = table.generate(table.rows.unwrap_or(default_rows), window, &tables, &mut rng);
+            tables.push(generated);
+        }
+        return Ok(tables);
+    }
+}
+
+impl TableSpec {
+    fn references(&self) -> impl Iterator<Item = &str> {
+        return self.columns.iter().filter_map(|c| match &c.generator {
+            ColumnGenerator::Reference { table, .. } => Some(table.as_str()),
+            _ => None,
+        });
+    }
+
+    /// Check every column against its type and the tables generated before this one
+    fn check(&self, earlier: &[TableSpec]) -> io::Result<()> {
+        if self.columns.is_empty() {
+            return Err(invalid(format!("Table {} has no columns", self.name)));
+        }
+        for (i, column) in self.columns.iter().enumerate() {
+            column.check(earlier, &self.columns[..i]).map_err(|msg| invalid(format!("{}.{}: {msg}", self.name, column.name)))?;
+        }
+        return Ok(());
+    }
+
+    fn generate(&self, rows: usize, window: (DateTime<Utc>, DateTime<Utc>), earlier: &[Table], rng: &mut StdRng) -> Table {
+        // The row each relation points at, drawn once per table
+        let mut relations: HashMap<String, UInt32Array> = HashMap::new();
+        let mut fields = Vec::with_capacity(self.columns.len());
+        let mut arrays: Vec<Arc<dyn Array>> = Vec::with_capacity(self.columns.len());
+
+        for column in &self.columns {
+            let array = match &column.generator {
+                ColumnGenerator::Reference { table, column: source, relation } => {
+                    let target = earlier.iter().find(|t| &t.name == table).expect("checked: references come after their table");
+                    let index = target.schema.fields.iter().position(|f| &f.name == source).expect("checked: the column exists");
+                    let values = target.chunk.arrays()[index].as_ref();
+                    let indices = {
+                        relations
+                            .entry(relation.clone().unwrap_or_else(|| table.clone()))
+                            .or_insert_with(|| UInt32Array::from_vec(random_indices(values.len(), rows, rng)))
+                    };
+                    take(values, indices).expect("indices are drawn below the table's row count")
+                }
+                ColumnGenerator::Expression { expr } => {
+                    let columns = fields.iter().map(|f: &Field| f.name.as_str()).zip(arrays.iter().map(|a| a.as_ref())).collect();
+                    let array = expr.evaluate(&columns, rows);
+                    // Whole numbers into a float column
+                    if array.data_type() == &DataType::Int64 && column.kind == ColumnType::Float {
+                        cast(array.as_ref(), &DataType::Float64, CastOptions::default()).expect("ints always cast to floats")
+                    }
+                    else { array }
+                }
+                _ => column.generate(rows, window, rng),
+            };
+            let array = if column.null_rate > 0.0 {
+                let drawn = Bitmap::from_iter((0..rows).map(|_| !rng.random_bool(column.null_rate)));
+                let validity = match array.validity() {
+                    Some(validity) => validity & &drawn,
+                    None => drawn,
+                };
+                array.with_validity(Some(validity))
+            }
+            else { array };
+            // References carry over the nulls of the column they point at
+            fields.push(Field::new(&column.name, array.data_type().clone(), array.validity().is_some()));
+            arrays.push(Arc::from(array));
+        }
+        return Table { name: self.name.clone(), schema: Schema::from(fields), chunk: Chunk::new(arrays) };
+    }
+}
+
+impl ColumnSpec {
+    /// `before` are the columns ahead of this one in its table
+    fn check(&self, earlier: &[TableSpec], before: &[ColumnSpec]) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.null_rate) {
+            return Err(format!("null_rate must be between 0 and 1: {}", self.null_rate));
+        }
+        match &self.generator {
+            ColumnGenerator::Sequence { .. } if self.kind != ColumnType::Int => {
+                return Err("sequence columns must be int".to_string());
+            }
+            ColumnGenerator::Uuid if self.kind != ColumnType::String => {
+                return Err("uuid columns must be string".to_string());
+            }
+            ColumnGenerator::Choice { values, weights } => {
+                literal_array(self.kind, values)?;
+                if let Some(weights) = weights {
+                    if weights.len() != values.len() {
+                        return Err(format!("{} weights for {} values", weights.len(), values.len()));
+                    }
+                    WeightedIndex::new(weights).map_err(|err| format!("Invalid weights: {err}"))?;
+                }
+            }
+            ColumnGenerator::Cycle { values } => {
+                literal_array(self.kind, values)?;
+            }
+            ColumnGenerator::Range { min, max } => match self.kind {
+                ColumnType::Int | ColumnType::Float => {
+                    let (Some(min), Some(max)) = (min, max)
+                    else { return Err("numeric ranges need a min and a max".to_string()); };
+                    let (min, max) = (as_float(min)?, as_float(max)?);
+                    if !min.is_finite() || !max.is_finite() {
+                        return Err(format!("range bounds must be finite: {min} to {max}"));
+                    }
+                    if !(max - min).is_finite() {
+                        return Err(format!("the range from {min} to {max} is too wide"));
+                    }
+                    if min > max {
+                        return Err(format!("min {min} is above max {max}"));
+                    }
+                    if self.kind == ColumnType::Int && (min.fract() != 0.0 || max.fract() != 0.0) {
+                        return Err("int ranges need whole bounds".to_string());
+                    }
+                }
+                ColumnType::Date | ColumnType::Timestamp => {
+                    for bound in [min, max].into_iter().flatten() {
+                        as_timestamp(bound)?;
+                    }
+                    if let (Some(min), Some(max)) = (min, max)
+                        && as_timestamp(min)? > as_timestamp(max)?
+                    {
+                        return Err("min is after max".to_string());
+                    }
+                }
+                ColumnType::String | ColumnType::Bool => {
+                    return Err("range columns must be int, float, date or timestamp".to_string());
+                }
+            },
+            ColumnGenerator::Reference { table, column, .. } => {
+                let Some(target) = earlier.iter().find(|t| &t.name == table)
+                else { return Err(format!("A table can't reference itself: {table}")); };
+                if target.rows == Some(0) {
+                    return Err(format!("{table} has no rows to reference"));
+                }
+                let Some(source) = target.columns.iter().find(|c| &c.name == column)
+                else { return Err(format!("No column {table}.{column}")); };
+                if source.kind != self.kind {
+                    return Err(format!("{table}.{column} is {:?}, not {:?}", source.kind, self.kind));
+                }
+            }
+            ColumnGenerator::Expression { expr } => {
+                let columns = before.iter().map(|c| (c.name.as_str(), c.kind.expr_type())).collect();
+                let result = expr.check(&columns)?;
+                if result != self.kind.expr_type() && !(result == ExprType::Int && self.kind == ColumnType::Float) {
+                    return Err(format!("The expression gives {result:?}, not {:?}", self.kind));
+                }
+            }
+            _ => {}
+        }
+        return Ok(());
+    }
+
+    /// Every generator but `reference` and `expression`, which need the other columns
+    fn generate(&self, rows: usize, window: (DateTime<Utc>, DateTime<Utc>), rng: &mut impl Rng) -> Box<dyn Array> {
+        return match &self.generator {
+            ColumnGenerator::Sequence { start, step } => {
+                Box::new(Int64Array::from_vec((0..rows as i64).map(|i| start + i * step).collect()))
+            }
+            ColumnGenerator::Uuid => {
+                let uuids = (0..rows).map(|_| generate_uuid(rng));
+                Box::new(Utf8Array::<i32>::from_iter_values(uuids))
+            }
+            ColumnGenerator::Choice { values, weights } => {
+                let indices: Vec<u32> = match weights {
+                    Some(weights) => {
+                        let weighted = WeightedIndex::new(weights).expect("checked: the weights are valid");
+                        (0..rows).map(|_| weighted.sample(rng) as u32).collect()
+                    }
+                    None => random_indices(values.len(), rows, rng),
+                };
+                let values = literal_array(self.kind, values).expect("checked: the values match the column type");
+                take(values.as_ref(), &UInt32Array::from_vec(indices)).expect("indices are drawn below the value count")
+            }
+            ColumnGenerator::Cycle { values } => {
+                let indices = UInt32Array::from_vec((0..rows).map(|i| (i % values.len()) as u32).collect());
+                let values = literal_array(self.kind, values).expect("checked: the values match the column type");
+                take(values.as_ref(), &indices).expect("indices wrap around the value count")
+            }
+            ColumnGenerator::Range { min, max } => self.generate_range(min, max, rows, window, rng),
+            ColumnGenerator::Reference { .. } | ColumnGenerator::Expression { .. } => {
+                unreachable!("references and expressions are resolved by the table")
+            }
+        };
+    }
+
+    fn generate_range(
+        &self,
+        min: &Option<Literal>,
+        max: &Option<Literal>,
+        rows: usize,
+        window: (DateTime<Utc>, DateTime<Utc>),
+        rng: &mut impl Rng,
+    ) -> Box<dyn Array> {
+        return match self.kind {
+            ColumnType::Int => {
+                let (min, max) = numeric_bounds(min, max);
+                let (min, max) = (min as i64, max as i64);
+                Box::new(Int64Array::from_vec((0..rows).map(|_| rng.random_range(min..=max)).collect()))
+            }
+            ColumnType::Float => {
+                let (min, max) = numeric_bounds(min, max);
+                Box::new(Float64Array::from_vec((0..rows).map(|_| rng.random_range(min..=max)).collect()))
+            }
+            ColumnType::Date | ColumnType::Timestamp => {
+                let (start, end) = time_bounds(min, max, window);
+                let millis: Vec<i64> = (0..rows).map(|_| rng.random_range(start..=end)).collect();
+                if self.kind == ColumnType::Date {
+                    let days = millis.iter().map(|ms| ms.div_euclid(MILLIS_PER_DAY) as i32).collect();
+                    Box::new(Int32Array::from_vec(days).to(DataType::Date32))
+                }
+                else { Box::new(Int64Array::from_vec(millis).to(timestamp_type())) }
+            }
+            ColumnType::String | ColumnType::Bool => unreachable!("checked when the spec was loaded"),
+        };
+    }
+}
+
+// ------------------------ Helpers ------------------------
+
+/// The values of a `choice` or `cycle`, as an array of the column's type
+fn literal_array(kind: ColumnType, values: &[Literal]) -> Result<Box<dyn Array>, String> {
+    if values.is_empty() {
+        return Err("no values to pick from".to_string());
+    }
+    return Ok(match kind {
+        ColumnType::Int => {
+            let ints = values.iter().map(|v| match v {
+                Literal::Int(i) => Ok(*i),
+                other => Err(format!("{other:?} is not an int")),
+            });
+            Box::new(Int64Array::from_vec(ints.collect::<Result<_, _>>()?))
+        }
+        ColumnType::Float => Box::new(Float64Array::from_vec(values.iter().map(as_float).collect::<Result<_, _>>()?)),
+        ColumnType::String => {
+            let strings = values.iter().map(|v| match v {
+                Literal::Text(s) => Ok(s.as_str()),
+                other => Err(format!("{other:?} is not a string")),
+            });
+            Box::new(Utf8Array::<i32>::from_slice(strings.collect::<Result<Vec<_>, _>>()?))
+        }
+        ColumnType::Bool => {
+            let bools = values.iter().map(|v| match v {
+                Literal::Bool(b) => Ok(*b),
+                other => Err(format!("{other:?} is not a bool")),
+            });
+            Box::new(BooleanArray::from_slice(bools.collect::<Result<Vec<_>, _>>()?))
+        }
+        ColumnType::Date => {
+            let days = values.iter().map(|v| Ok(as_timestamp(v)?.div_euclid(MILLIS_PER_DAY) as i32));
+            Box::new(Int32Array::from_vec(days.collect::<Result<_, String>>()?).to(DataType::Date32))
+        }
+        ColumnType::Timestamp => {
+            Box::new(Int64Array::from_vec(values.iter().map(as_timestamp).collect::<Result<_, _>>()?).to(timestamp_type()))
+        }
+    });
+}
+
+/// Milliseconds since the epoch of a date or timestamp range's bounds, each
+/// defaulting to the simulated period's
+fn time_bounds(min: &Option<Literal>, max: &Option<Literal>, window: (DateTime<Utc>, DateTime<Utc>)) -> (i64, i64) {
+    let bound = |bound: &Literal| as_timestamp(bound).expect("checked: range bounds are dates");
+    let start = min.as_ref().map_or(window.0.timestamp_millis(), bound);
+    let end = max.as_ref().map_or(window.1.timestamp_millis(), bound);
+    return (start, end);
+}
+
+/// The bounds of a numeric range, which `ColumnSpec::check` made sure are
+/// both there, finite and in order
+fn numeric_bounds(min: &Option<Literal>, max: &Option<Literal>) -> (f64, f64) {
+    let bound = |bound: &Option<Literal>| {
+        bound.as_ref().and_then(|bound| as_float(bound).ok()).expect("checked: numeric ranges have both bounds")
+    };
+    return (bound(min), bound(max));
+}
+
+fn as_float(value: &Literal) -> Result<f64, String> {
+    return match value {
+        Literal::Int(i) => Ok(*i as f64),
+        Literal::Float(f) => Ok(*f),
+        other => Err(format!("{other:?} is not a number")),
+    };
+}
+
+/// Milliseconds since the epoch of a `YYYY-MM-DD` date or an RFC 3339 timestamp
+fn as_timestamp(value: &Literal) -> Result<i64, String> {
+    let Literal::Text(text) = value
+    else { return Err(format!("{value:?} is not a date")); };
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc().timestamp_millis());
+    }
+    return {
+        DateTime::parse_from_rfc3339(text)
+            .map(|t| t.timestamp_millis())
+            .map_err(|_| format!("Invalid date: {text} (expected YYYY-MM-DD or RFC 3339)"))
+    };
+}
+
+fn random_indices(len: usize, rows: usize, rng: &mut impl Rng) -> Vec<u32> {
+    return (0..rows).map(|_| rng.random_range(0..len as u32)).collect();
+}
+
+fn timestamp_type() -> DataType {
+    return DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".to_string()));
+}
+
+fn invalid(msg: String) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, msg);
+}
+
+// ------------------------ Static Data ------------------------
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+const DEFAULT_SPEC: &str = include_str!("../default_spec.toml");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customers;
+    use crate::products;
+
+    fn window() -> (DateTime<Utc>, DateTime<Utc>) {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        return (day(1), day(31));
+    }
+
+    fn column<'a, T: 'static>(table: &'a Table, name: &str) -> &'a T {
+        let index = table.schema.fields.iter().position(|f| f.name == name).unwrap();
+        return table.chunk.arrays()[index].as_any().downcast_ref::<T>().unwrap();
+    }
+
+    #[test]
+    fn builtin_matches_the_order_model() {
+        let tables = Spec::builtin().generate(1, window(), 7).unwrap();
+        let table = |name: &str| tables.iter().find(|t| t.name == name).unwrap();
+
+        let customers = table("customers");
+        let all = customers::all_customers().unwrap();
+        let ids = column::<Int64Array>(customers, "id");
+        assert_eq!(ids.len(), all.len());
+        for (row, id) in ids.values_iter().enumerate() {
+            let customer = all.iter().find(|c| c.id == *id).unwrap();
+            let text = |name| column::<Utf8Array<i32>>(customers, name).value(row);
+            assert_eq!(text("name"), customer.name);
+            assert_eq!(text("email"), customer.email);
+            assert_eq!(text("address"), customer.address);
+            assert_eq!(text("country"), customer.country);
+            assert_eq!(text("timezone"), customer.timezone);
+            assert_eq!(text("status"), format!("{:?}", customer.status));
+        }
+
+        let products = table("products");
+        let ids = column::<Int64Array>(products, "id");
+        assert_eq!(ids.len(), products::PRODUCT_COUNT);
+        for (row, id) in ids.values_iter().enumerate() {
+            let product = products::product_at(*id as usize);
+            assert_eq!(column::<Utf8Array<i32>>(products, "name").value(row), product.name);
+            assert_eq!(column::<Utf8Array<i32>>(products, "category").value(row), product.category.to_string());
+            assert_eq!(column::<Float64Array>(products, "price").value(row), product.price);
+            assert_eq!(column::<Float64Array>(products, "weight").value(row), product.weight);
+        }
+    }
+
+    #[test]
+    fn references_keep_the_nulls_they_point_at() {
+        let spec = Spec::parse(
+            r#"
+            [[table]]
+            name = "source"
+            rows = 50
+
+            [[table.column]]
+            name = "id"
+            type = "int"
+            generator = "sequence"
+            start = 0
+
+            [[table.column]]
+            name = "value"
+            type = "int"
+            generator = "range"
+            min = 1
+            max = 9
+            null_rate = 0.5
+
+            [[table]]
+            name = "target"
+            rows = 500
+
+            [[table.column]]
+            name = "source_id"
+            type = "int"
+            generator = "reference"
+            table = "source"
+            column = "id"
+
+            [[table.column]]
+            name = "value"
+            type = "int"
+            generator = "reference"
+            table = "source"
+            column = "value"
+            null_rate = 0.5
+            "#,
+        )
+        .unwrap();
+        let tables = spec.generate(0, window(), 7).unwrap();
+        let source = column::<Int64Array>(&tables[0], "value");
+        let target = &tables[1];
+        let values = column::<Int64Array>(target, "value");
+        for (row, id) in column::<Int64Array>(target, "source_id").values_iter().enumerate() {
+            if source.is_null(*id as usize) {
+                assert!(values.is_null(row), "row {row} lost the null of source row {id}");
+            }
+            else if values.is_valid(row) {
+                assert_eq!(values.value(row), source.value(*id as usize));
+            }
+        }
+    }
+
+    #[test]
+    fn date_ranges_must_not_end_before_they_start() {
+        let spec = |bounds: &str| {
+            let text = format!(
+                "[[table]]\nname = \"t\"\nrows = 10\n\n[[table.column]]\nname = \"day\"\ntype = \"date\"\ngenerator = \"range\"\n{bounds}"
+            );
+            return Spec::parse(&text);
+        };
+        assert!(spec("min = \"2024-03-10\"\nmax = \"2024-03-01\"").is_err());
+        assert!(spec("min = \"2024-03-01\"\nmax = \"2024-03-10\"").is_ok());
+
+        let after_the_window = spec("min = \"2025-01-01\"").unwrap();
+        assert!(matches!(after_the_window.generate(0, window(), 7), Err(Error::Config(_))));
+        let inside_the_window = spec("min = \"2024-03-15\"").unwrap();
+        assert!(inside_the_window.generate(0, window(), 7).is_ok());
+    }
+
+    #[test]
+    fn numeric_ranges_must_be_finite() {
+        let spec = |bounds: &str| {
+            let text = format!(
+                "[[table]]\nname = \"t\"\nrows = 10\n\n[[table.column]]\nname = \"x\"\ntype = \"float\"\ngenerator = \"range\"\n{bounds}"
+            );
+            return Spec::parse(&text);
+        };
+        assert!(spec("min = nan\nmax = 1.0").is_err());
+        assert!(spec("min = 0.0\nmax = inf").is_err());
+        assert!(spec("min = -1.7e308\nmax = 1.7e308").is_err());
+        assert!(spec("min = -1.5\nmax = 2.5").unwrap().generate(0, window(), 7).is_ok());
+    }
+
+    #[test]
+    fn references_need_rows_to_point_at() {
+        let spec = Spec::parse(
+            r#"
+            [[table]]
+            name = "source"
+
+            [[table.column]]
+            name = "id"
+            type = "int"
+            generator = "sequence"
+
+            [[table]]
+            name = "target"
+            rows = 10
+
+            [[table.column]]
+            name = "source_id"
+            type = "int"
+            generator = "reference"
+            table = "source"
+            column = "id"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(spec.generate(0, window(), 7), Err(Error::Generation(_))));
+        assert_eq!(spec.generate(5, window(), 7).unwrap()[1].chunk.len(), 10);
+    }
+}
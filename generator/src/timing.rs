@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::pricing::state_code;
+use crate::utils::generate_datetime;
+
+/// A fixed-date holiday that lifts order volume over the `window_days`
+/// leading up to it, and pulls one seasonal product into more baskets
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Holiday {
+    pub name: String,
+    pub month: u32,
+    pub day: u32,
+    pub window_days: i64,
+    /// Volume multiplier on the holiday itself; ramps up linearly from 1.0
+    pub boost: f64,
+    pub product_id: Option<i64>,
+    /// Share of orders buying `product_id` on the holiday itself; ramps up like `boost`
+    #[serde(default)]
+    pub product_share: f64,
+}
+
+/// Relative order intensity by weekday, month and local hour, plus holidays.
+/// Weights are relative to each other; only their ratios matter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimeModel {
+    /// Monday through Sunday
+    pub weekday_weights: [f64; 7],
+    /// January through December
+    pub month_weights: [f64; 12],
+    /// Hours 0-23 in the customer's local time
+    pub hour_weights: [f64; 24],
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+    /// Year-over-year growth in order volume, e.g. 0.1 for 10% more orders each year
+    #[serde(default)]
+    pub annual_growth: f64,
+}
+
+impl Default for TimeModel {
+    fn default() -> Self {
+        let holiday = |name: &str, month, day, window_days, boost, product_id, product_share| Holiday {
+            name: name.to_string(),
+            month,
+            day,
+            window_days,
+            boost,
+            product_id,
+            product_share,
+        };
+        return TimeModel {
+            weekday_weights: [0.95, 0.9, 0.9, 0.95, 1.1, 1.3, 1.2],
+            month_weights: [0.8, 0.75, 0.9, 0.95, 1.0, 1.0, 1.05, 1.0, 0.95, 1.0, 1.25, 1.45],
+            hour_weights: [
+                0.15, 0.08, 0.05, 0.04, 0.04, 0.08, 0.2, 0.45, 0.75, 1.0, 1.2, 1.3,
+                1.35, 1.25, 1.1, 1.0, 1.0, 1.05, 1.2, 1.4, 1.45, 1.2, 0.8, 0.4,
+            ],
+            holidays: vec![
+                holiday("Christmas", 12, 24, 35, 1.8, Some(20), 0.2),
+                holiday("Halloween", 10, 31, 21, 1.3, Some(21), 0.15),
+                holiday("Independence Day", 7, 4, 14, 1.15, Some(22), 0.1),
+            ],
+            annual_growth: 0.1,
+        };
+    }
+}
+
+impl TimeModel {
+    pub fn load(path: &str) -> io::Result<TimeModel> {
+        let reader = BufReader::new(File::open(path)?);
+        return serde_json::from_reader(reader).map_err(io::Error::other);
+    }
+
+    /// Draw an order timestamp in `[start, end)` for a customer `utc_offset`
+    /// hours from UTC, by rejection sampling against the model's intensity.
+    pub fn sample(&self, start: DateTime<Utc>, end: DateTime<Utc>, utc_offset: i32, rng: &mut impl Rng) -> DateTime<Utc> {
+        let ceiling = self.max_intensity() * self.growth(start, start).max(self.growth(start, end));
+        loop {
+            let candidate = generate_datetime(start, end, rng);
+            let local = candidate + Duration::hours(utc_offset as i64);
+            let intensity = self.intensity(local) * self.growth(start, candidate);
+            if rng.random_range(0.0..ceiling) < intensity {
+                return candidate;
+            }
+        }
+    }
+
+    /// The seasonal product an order on `date` should buy, if any
+    pub fn seasonal_product(&self, date: DateTime<Utc>, rng: &mut impl Rng) -> Option<i64> {
+        for holiday in &self.holidays {
+            let Some(product_id) = holiday.product_id else { continue };
+            let share = holiday.product_share * ramp(holiday, date.date_naive());
+            if share > 0.0 && rng.random_bool(share.min(1.0)) {
+                return Some(product_id);
+            }
+        }
+        return None;
+    }
+
+    fn intensity(&self, local: DateTime<Utc>) -> f64 {
+        let weekday = self.weekday_weights[local.weekday().num_days_from_monday() as usize];
+        let month = self.month_weights[local.month0() as usize];
+        let hour = self.hour_weights[local.hour() as usize];
+        let holidays: f64 = {
+            self.holidays
+                .iter()
+                .map(|h| 1.0 + (h.boost - 1.0) * ramp(h, local.date_naive()))
+                .product()
+        };
+        return weekday * month * hour * holidays;
+    }
+
+    /// Compounded growth factor from `start` to `date`
+    fn growth(&self, start: DateTime<Utc>, date: DateTime<Utc>) -> f64 {
+        let years = (date - start).num_seconds() as f64 / SECONDS_PER_YEAR;
+        return (1.0 + self.annual_growth).max(0.0).powf(years);
+    }
+
+    fn max_intensity(&self) -> f64 {
+        let max = |weights: &[f64]| weights.iter().cloned().fold(0.0, f64::max);
+        let holidays: f64 = self.holidays.iter().map(|h| h.boost.max(1.0)).product();
+        return max(&self.weekday_weights) * max(&self.month_weights) * max(&self.hour_weights) * holidays;
+    }
+}
+
+/// 0.0 outside the holiday window, rising linearly to 1.0 on the holiday.
+/// The window can reach back across New Year, so the next year's holiday counts too.
+fn ramp(holiday: &Holiday, day: NaiveDate) -> f64 {
+    if holiday.window_days <= 0 {
+        return 0.0;
+    }
+    return {
+        [day.year(), day.year() + 1]
+            .into_iter()
+            .filter_map(|year| NaiveDate::from_ymd_opt(year, holiday.month, holiday.day))
+            .map(|date| (date - day).num_days())
+            .filter(|days_until| (0..=holiday.window_days).contains(days_until))
+            .map(|days_until| 1.0 - days_until as f64 / holiday.window_days as f64)
+            .fold(0.0, f64::max)
+    };
+}
+
+// ------------------------ Time Zones ------------------------
+
+/// IANA time zone for a customer, from their state in the US or their country
+/// abroad. Falls back to US Eastern.
+pub fn timezone_for(country: &str, address: &str) -> &'static str {
+    let (key, zones) = {
+        if country == "US" { (state_code(address).unwrap_or_default(), &STATE_TIMEZONES[..]) }
+        else { (country, &COUNTRY_TIMEZONES[..]) }
+    };
+    return {
+        zones
+            .iter()
+            .find(|(k, _, _)| *k == key)
+            .map(|(_, tz, _)| *tz)
+            .unwrap_or("America/New_York")
+    };
+}
+
+/// Standard-time offset from UTC in hours; daylight saving is not modeled
+pub fn utc_offset(timezone: &str) -> i32 {
+    return {
+        STATE_TIMEZONES
+            .iter()
+            .chain(COUNTRY_TIMEZONES.iter())
+            .find(|(_, tz, _)| *tz == timezone)
+            .map(|(_, _, offset)| *offset)
+            .unwrap_or(-5)
+    };
+}
+
+// ------------------------ Static Data ------------------------
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0;
+
+/// (state code, IANA zone, standard UTC offset)
+const STATE_TIMEZONES: [(&str, &str, i32); 25] = [
+    ("AK", "America/Anchorage", -9), ("AZ", "America/Phoenix", -7), ("CA", "America/Los_Angeles", -8),
+    ("CO", "America/Denver", -7), ("FL", "America/New_York", -5), ("GA", "America/New_York", -5),
+    ("IA", "America/Chicago", -6), ("ID", "America/Boise", -7), ("IN", "America/Indiana/Indianapolis", -5),
+    ("MN", "America/Chicago", -6), ("MO", "America/Chicago", -6), ("NC", "America/New_York", -5),
+    ("NE", "America/Chicago", -6), ("NV", "America/Los_Angeles", -8), ("NY", "America/New_York", -5),
+    ("OH", "America/New_York", -5), ("OR", "America/Los_Angeles", -8), ("SC", "America/New_York", -5),
+    ("TN", "America/Chicago", -6), ("TX", "America/Chicago", -6), ("UT", "America/Denver", -7),
+    ("VA", "America/New_York", -5), ("VT", "America/New_York", -5), ("WA", "America/Los_Angeles", -8),
+    ("WI", "America/Chicago", -6),
+];
+
+/// (country code, IANA zone, standard UTC offset)
+const COUNTRY_TIMEZONES: [(&str, &str, i32); 7] = [
+    ("CA", "America/Toronto", -5), ("GB", "Europe/London", 0), ("DE", "Europe/Berlin", 1),
+    ("FR", "Europe/Paris", 1), ("JP", "Asia/Tokyo", 9), ("MX", "America/Mexico_City", -6),
+    ("AU", "Australia/Sydney", 10),
+];
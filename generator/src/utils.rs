@@ -1,13 +1,17 @@
-use uuid::Uuid;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
 use chrono::{DateTime, Duration, Utc, Datelike, TimeZone};
 use rand::Rng;
-use rand::rng;
 
-pub fn generate_uuid() -> String {
-    return Uuid::new_v4().to_string();
+/// A random (version 4) UUID drawn from `rng`
+pub fn generate_uuid(rng: &mut impl Rng) -> String {
+    return uuid::Builder::from_random_bytes(rng.random()).into_uuid().to_string();
 }
 
-pub fn generate_datetime() -> DateTime<Utc> {
+/// The simulated period: January 1 of the current year until a week from now
+pub fn simulation_window() -> (DateTime<Utc>, DateTime<Utc>) {
     let now = Utc::now();
     let current_year = now.date_naive().year();
     let start = {
@@ -15,9 +19,12 @@ pub fn generate_datetime() -> DateTime<Utc> {
             .single()
             .expect("Invalid date")
     };
-    let end = now + Duration::days(7);
+    return (start, now + Duration::days(7));
+}
+
+/// A uniformly random moment in `[start, end)`
+pub fn generate_datetime(start: DateTime<Utc>, end: DateTime<Utc>, rng: &mut impl Rng) -> DateTime<Utc> {
     let seconds_range = end.timestamp() - start.timestamp();
-    let mut rng = rng();
     let random_seconds = rng.random_range(0..seconds_range);
     return start + Duration::seconds(random_seconds);
 }
@@ -26,3 +33,26 @@ pub fn round_decimal(val: f64) -> f64 {
     return (val * 100.0).round() / 100.0;
 }
 
+/// Write `path` through a hidden temporary file beside it, renamed into place
+/// once `write` succeeds and the data is on disk. A failed or interrupted
+/// write never leaves a truncated file at `path`.
+pub fn write_atomic<E: From<io::Error>>(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), E>,
+) -> Result<(), E> {
+    let name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let temp = path.with_file_name(format!(".{name}.tmp"));
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        write(&mut writer)?;
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)?;
+        return Ok(());
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    return result;
+}
+
@@ -1,7 +1,6 @@
 use arrow2::array::*;
-use std::fs::File;
 use std::sync::Arc;
-use std::io::BufWriter;
+use std::path::{Path, PathBuf};
 use arrow2::chunk::Chunk;
 use arrow2::datatypes::*;
 use arrow2::io::parquet::write::*;
@@ -9,174 +8,1105 @@ use arrow2::io::parquet::write::to_parquet_leaves;
 use arrow2::io::parquet::write::CompressionOptions;
 use arrow2::array::{Array, Int64Array, StructArray, Utf8Array};
 use arrow2::datatypes::{DataType, Field};
+use arrow2::offset::Offsets;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
+use crate::clickstream::{Session, SessionEvent};
+use crate::config::Config;
+use crate::customers::{Customer, CustomerHistory};
+use crate::dataset::Dataset;
+use crate::error;
+use crate::fraud::FraudLabel;
+use crate::fx::FxTable;
+use crate::integrity::{self, IntegrityCounts, IntegrityPlan};
+use crate::inventory::InventorySnapshot;
 use crate::orders::Order;
+use crate::payments::{InstrumentBook, InstrumentKind, Payment};
+use crate::products::Product;
+use crate::registry::{Table, Tabular};
+use crate::returns::Return;
+use crate::reviews::Review;
+use crate::shipments::Shipment;
+use crate::utils;
 
-/// Convert a list of orders into an Arrow Chunk (table-like columnar batch)
-pub fn orders_to_chunk(orders: &[Order]) -> Chunk<Arc<dyn Array>> {
+/// Convert a list of orders into an Arrow Chunk (table-like columnar batch).
+/// Fails if there are too many payment attempts to index.
+pub fn orders_to_chunk(orders: &[Order]) -> arrow2::error::Result<Chunk<Arc<dyn Array>>> {
     let id_array = Utf8Array::<i32>::from_slice(orders.iter().map(|o| o.id.as_str()).collect::<Vec<_>>());
-    let customer_array = get_customer_array(orders);
-    let product_array = get_product_array(orders);
-    let payment_array = get_payment_array(orders);
+    let customer_array = get_customer_array(&orders.iter().map(|o| &o.customer).collect::<Vec<_>>());
+    let product_array = get_product_array(&orders.iter().map(|o| &o.product).collect::<Vec<_>>());
+    let payment_array = get_payment_array(&orders.iter().map(|o| &o.payment).collect::<Vec<_>>());
     let discount_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.discount)));
+    let discount_reason_array = Utf8Array::<i32>::from_slice(orders.iter().map(|o| o.discount_reason.as_str()).collect::<Vec<_>>());
     let quantity_array = UInt32Array::from_iter(orders.iter().map(|o| Some(o.quantity)));
+    let subtotal_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.subtotal)));
+    let discount_amount_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.discount_amount)));
+    let tax_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.tax)));
+    let shipping_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.shipping)));
     let total_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.total)));
+    let currency_array = Utf8Array::<i32>::from_slice(orders.iter().map(|o| o.currency.as_str()).collect::<Vec<_>>());
+    let total_local_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.total_local)));
+    let total_usd_array = Float64Array::from_iter(orders.iter().map(|o| Some(o.total_usd)));
     let date_array = Utf8Array::<i32>::from_slice(orders.iter().map(|o| o.date.to_rfc3339()).collect::<Vec<_>>());
     let status_array = Utf8Array::<i32>::from_slice(orders.iter().map(|o| format!("{:?}", o.status)).collect::<Vec<_>>());
+    let cancel_reason_array = Utf8Array::<i32>::from(orders.iter().map(|o| o.cancel_reason.as_deref()).collect::<Vec<_>>());
+    let restocked_at_array = Utf8Array::<i32>::from(orders.iter().map(|o| o.restocked_at.map(|d| d.to_rfc3339())).collect::<Vec<_>>());
+    let attempts_array = get_attempts_array(orders)?;
+    let session_id_array = Utf8Array::<i32>::from(orders.iter().map(|o| o.session_id.as_deref()).collect::<Vec<_>>());
 
-    return Chunk::new(vec![
+    return Ok(Chunk::new(vec![
         Arc::new(id_array),
-        customer_array,
-        product_array,
-        payment_array,
+        Arc::new(customer_array),
+        Arc::new(product_array),
+        Arc::new(payment_array),
         Arc::new(discount_array),
+        Arc::new(discount_reason_array),
         Arc::new(quantity_array),
+        Arc::new(subtotal_array),
+        Arc::new(discount_amount_array),
+        Arc::new(tax_array),
+        Arc::new(shipping_array),
         Arc::new(total_array),
+        Arc::new(currency_array),
+        Arc::new(total_local_array),
+        Arc::new(total_usd_array),
         Arc::new(date_array),
         Arc::new(status_array),
+        Arc::new(cancel_reason_array),
+        attempts_array,
+        Arc::new(restocked_at_array),
+        Arc::new(session_id_array),
+    ]));
+}
+
+fn get_attempt_type() -> DataType {
+    return DataType::Struct(vec![
+        Field::new("transaction_id", DataType::Utf8, false),
+        Field::new("instrument_id", DataType::Utf8, false),
+        Field::new("attempted_at", DataType::Utf8, false),
+        Field::new("approved", DataType::Boolean, false),
+        Field::new("decline_code", DataType::Utf8, true),
     ]);
 }
 
-pub fn get_payment_array(orders: &[Order]) -> Arc<dyn Array> {
+/// Every order's authorization attempts as a list of structs
+pub fn get_attempts_array(orders: &[Order]) -> arrow2::error::Result<Arc<dyn Array>> {
+    let attempts: Vec<_> = orders.iter().flat_map(|o| o.attempts.iter()).collect();
     let transaction_id_array = Utf8Array::<i32>::from_slice(
-        orders
+        attempts.iter().map(|a| a.transaction_id.as_str()).collect::<Vec<_>>()
+    );
+    let instrument_id_array = Utf8Array::<i32>::from_slice(
+        attempts.iter().map(|a| a.instrument_id.as_str()).collect::<Vec<_>>()
+    );
+    let attempted_at_array = Utf8Array::<i32>::from_slice(
+        attempts.iter().map(|a| a.attempted_at.to_rfc3339()).collect::<Vec<_>>()
+    );
+    let approved_array = BooleanArray::from_slice(
+        attempts.iter().map(|a| a.approved).collect::<Vec<_>>()
+    );
+    let decline_code_array = Utf8Array::<i32>::from(
+        attempts.iter().map(|a| a.decline_code.map(|c| format!("{c:?}"))).collect::<Vec<_>>()
+    );
+    let struct_array = StructArray::new(
+        get_attempt_type(),
+        vec![
+            Box::new(transaction_id_array) as Box<dyn Array>,
+            Box::new(instrument_id_array),
+            Box::new(attempted_at_array),
+            Box::new(approved_array),
+            Box::new(decline_code_array),
+        ],
+        None,
+    );
+    let offsets = Offsets::<i32>::try_from_lengths(orders.iter().map(|o| o.attempts.len()))?;
+    let list_array = ListArray::<i32>::new(
+        get_attempts_type(),
+        offsets.into(),
+        Box::new(struct_array),
+        None,
+    );
+    return Ok(Arc::new(list_array));
+}
+
+fn get_attempts_type() -> DataType {
+    return DataType::List(Box::new(Field::new("item", get_attempt_type(), false)));
+}
+
+fn get_payment_type() -> DataType {
+    return DataType::Struct(vec![
+        Field::new("transaction_id", DataType::Utf8, false),
+        Field::new("instrument_id", DataType::Utf8, false),
+        Field::new("method", DataType::Utf8, false),
+        Field::new("network", DataType::Utf8, false),
+        Field::new("masked_number", DataType::Utf8, false),
+        Field::new("wallet_provider", DataType::Utf8, true),
+        Field::new("expiration", DataType::Utf8, false),
+    ]);
+}
+
+fn get_product_type() -> DataType {
+    return DataType::Struct(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("weight", DataType::Float64, false),
+    ]);
+}
+
+fn get_customer_type() -> DataType {
+    return DataType::Struct(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("email", DataType::Utf8, false),
+        Field::new("address", DataType::Utf8, false),
+        Field::new("country", DataType::Utf8, false),
+        Field::new("timezone", DataType::Utf8, false),
+        Field::new("status", DataType::Utf8, false),
+    ]);
+}
+
+pub fn get_payment_array(payments: &[&Payment]) -> StructArray {
+    let transaction_id_array = Utf8Array::<i32>::from_slice(
+        payments
             .iter()
-            .map(|o| o.payment.transaction_id.as_str())
+            .map(|p| p.transaction_id.as_str())
+            .collect::<Vec<_>>()
+    );
+    let instrument_id_array = Utf8Array::<i32>::from_slice(
+        payments
+            .iter()
+            .map(|p| p.instrument_id.as_str())
+            .collect::<Vec<_>>()
+    );
+    let method_array = Utf8Array::<i32>::from_slice(
+        payments
+            .iter()
+            .map(|p| p.details.method())
+            .collect::<Vec<_>>()
+    );
+    let network_array = Utf8Array::<i32>::from_slice(
+        payments
+            .iter()
+            .map(|p| format!("{:?}", p.details.network()))
+            .collect::<Vec<_>>()
+    );
+    let masked_number_array = Utf8Array::<i32>::from_slice(
+        payments
+            .iter()
+            .map(|p| p.details.masked_number())
+            .collect::<Vec<_>>()
+    );
+    let wallet_provider_array = Utf8Array::<i32>::from(
+        payments
+            .iter()
+            .map(|p| p.details.wallet_provider().map(|p| format!("{p:?}")))
             .collect::<Vec<_>>()
     );
-
     let expiration_array = Utf8Array::<i32>::from_slice(
-        orders
+        payments
             .iter()
-            .map(|o| o.payment.details.expiration_date().to_string())
+            .map(|p| p.details.expiration_date().to_string())
             .collect::<Vec<_>>()
     );
 
     let struct_array = StructArray::new(
-        DataType::Struct(vec![
-            Field::new("transaction_id", DataType::Utf8, false),
-            Field::new("expiration", DataType::Utf8, false),
-        ]),
+        get_payment_type(),
         vec![
             Box::new(transaction_id_array) as Box<dyn Array>,
+            Box::new(instrument_id_array),
+            Box::new(method_array),
+            Box::new(network_array),
+            Box::new(masked_number_array),
+            Box::new(wallet_provider_array),
             Box::new(expiration_array),
         ],
         None,
     );
-    return Arc::new(struct_array);
+    return struct_array;
 }
 
-pub fn get_product_array(orders: &[Order]) -> Arc<dyn Array> {
+pub fn get_product_array(products: &[&Product]) -> StructArray {
     let product_id_array = Int64Array::from_slice(
-        orders.iter().map(|o| o.product.id).collect::<Vec<_>>(),
+        products.iter().map(|p| p.id).collect::<Vec<_>>(),
     );
     let product_name_array = Utf8Array::<i32>::from_slice(
-        orders.iter().map(|o| o.product.name.as_str()).collect::<Vec<_>>(),
+        products.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
     );
     let product_category_array = Utf8Array::<i32>::from_slice(
-        orders.iter().map(|o| format!("{}", o.product.category)).collect::<Vec<_>>(),
+        products.iter().map(|p| format!("{}", p.category)).collect::<Vec<_>>(),
     );
     let product_price_array = Float64Array::from_iter(
-        orders.iter().map(|o| Some(o.product.price)),
+        products.iter().map(|p| Some(p.price)),
+    );
+    let product_weight_array = Float64Array::from_iter(
+        products.iter().map(|p| Some(p.weight)),
     );
     let struct_array = StructArray::new(
-        DataType::Struct(vec![
-            Field::new("id", DataType::Int64, false),
-            Field::new("name", DataType::Utf8, false),
-            Field::new("category", DataType::Utf8, false),
-            Field::new("price", DataType::Float64, false),
-        ]),
+        get_product_type(),
         vec![
             Box::new(product_id_array) as Box<dyn Array>,
             Box::new(product_name_array),
             Box::new(product_category_array),
             Box::new(product_price_array),
+            Box::new(product_weight_array),
         ],
         None,
     );
-    return Arc::new(struct_array);
+    return struct_array;
 }
 
-pub fn get_customer_array(orders: &[Order]) -> Arc<dyn Array> {
+pub fn get_customer_array(customers: &[&Customer]) -> StructArray {
     let customer_id_array = {
         Int64Array::from_slice(
-            orders.iter().map(|o| o.customer.id).collect::<Vec<_>>()
+            customers.iter().map(|c| c.id).collect::<Vec<_>>()
         )
     };
     let customer_name_array = {
         Utf8Array::<i32>::from_slice(
-            orders.iter().map(|o| o.customer.name.as_str()).collect::<Vec<_>>()
+            customers.iter().map(|c| c.name.as_str()).collect::<Vec<_>>()
         )
     };
     let customer_email_array = {
         Utf8Array::<i32>::from_slice(
-            orders.iter().map(|o| o.customer.email.as_str()).collect::<Vec<_>>()
+            customers.iter().map(|c| c.email.as_str()).collect::<Vec<_>>()
         )
     };
     let customer_address_array = {
         Utf8Array::<i32>::from_slice(
-            orders.iter().map(|o| o.customer.address.as_str()).collect::<Vec<_>>()
+            customers.iter().map(|c| c.address.as_str()).collect::<Vec<_>>()
+        )
+    };
+    let customer_country_array = {
+        Utf8Array::<i32>::from_slice(
+            customers.iter().map(|c| c.country.as_str()).collect::<Vec<_>>()
+        )
+    };
+    let customer_timezone_array = {
+        Utf8Array::<i32>::from_slice(
+            customers.iter().map(|c| c.timezone.as_str()).collect::<Vec<_>>()
         )
     };
     let customer_status_array = {
         Utf8Array::<i32>::from_slice(
-            orders.iter().map(|o| format!("{:?}", o.customer.status)).collect::<Vec<_>>()
+            customers.iter().map(|c| format!("{:?}", c.status)).collect::<Vec<_>>()
         )
     };
     let customer_array = StructArray::new(
-        DataType::Struct(vec![
-            Field::new("id", DataType::Int64, false),
-            Field::new("name", DataType::Utf8, false),
-            Field::new("email", DataType::Utf8, false),
-            Field::new("address", DataType::Utf8, false),
-            Field::new("status", DataType::Utf8, false),
-        ]),
+        get_customer_type(),
         vec![
             Box::new(customer_id_array),
             Box::new(customer_name_array),
             Box::new(customer_email_array),
             Box::new(customer_address_array),
+            Box::new(customer_country_array),
+            Box::new(customer_timezone_array),
             Box::new(customer_status_array),
         ],
         None,
     );
-    return Arc::new(customer_array);
+    return customer_array;
 }
 
 pub fn get_order_schema() -> Schema {
     return Schema::from(vec![
         Field::new("id", DataType::Utf8, false),
-        Field::new("customer", DataType::Struct(vec![
-            Field::new("id", DataType::Int64, false),
-            Field::new("name", DataType::Utf8, false),
-            Field::new("email", DataType::Utf8, false),
-            Field::new("address", DataType::Utf8, false),
-            Field::new("status", DataType::Utf8, false),
-        ]), false),
-        Field::new("product", DataType::Struct(vec![
-            Field::new("id", DataType::Int64, false),
-            Field::new("name", DataType::Utf8, false),
-            Field::new("category", DataType::Utf8, false),
-            Field::new("price", DataType::Float64, false),
-        ]), false),
-        Field::new("payment", DataType::Struct(vec![
-            Field::new("transaction_id", DataType::Utf8, false),
-            Field::new("expiration", DataType::Utf8, false),
-        ]), false),
+        Field::new("customer", get_customer_type(), false),
+        Field::new("product", get_product_type(), false),
+        Field::new("payment", get_payment_type(), false),
         Field::new("discount", DataType::Float64, true),
+        Field::new("discount_reason", DataType::Utf8, false),
         Field::new("quantity", DataType::UInt32, false),
+        Field::new("subtotal", DataType::Float64, false),
+        Field::new("discount_amount", DataType::Float64, false),
+        Field::new("tax", DataType::Float64, false),
+        Field::new("shipping", DataType::Float64, false),
         Field::new("total", DataType::Float64, false),
+        Field::new("currency", DataType::Utf8, false),
+        Field::new("total_local", DataType::Float64, false),
+        Field::new("total_usd", DataType::Float64, false),
         Field::new("date", DataType::Utf8, false),
         Field::new("status", DataType::Utf8, false),
+        Field::new("cancel_reason", DataType::Utf8, true),
+        Field::new("attempts", get_attempts_type(), false),
+        Field::new("restocked_at", DataType::Utf8, true),
+        Field::new("session_id", DataType::Utf8, true),
+    ]);
+}
+
+pub fn get_customer_history_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, false),
+        Field::new("email", DataType::Utf8, false),
+        Field::new("address", DataType::Utf8, false),
+        Field::new("country", DataType::Utf8, false),
+        Field::new("timezone", DataType::Utf8, false),
+        Field::new("status", DataType::Utf8, false),
+        Field::new("version", DataType::UInt32, false),
+        Field::new("effective_from", DataType::Utf8, false),
+        Field::new("effective_to", DataType::Utf8, true),
+        Field::new("is_current", DataType::Boolean, false),
+    ]);
+}
+
+/// Convert every customer version into an SCD Type 2 style chunk
+pub fn customer_history_to_chunk(history: &CustomerHistory) -> Chunk<Arc<dyn Array>> {
+    let versions: Vec<_> = history.versions().collect();
+    let id_array = Int64Array::from_slice(versions.iter().map(|v| v.customer.id).collect::<Vec<_>>());
+    let name_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| v.customer.name.as_str()).collect::<Vec<_>>());
+    let email_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| v.customer.email.as_str()).collect::<Vec<_>>());
+    let address_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| v.customer.address.as_str()).collect::<Vec<_>>());
+    let country_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| v.customer.country.as_str()).collect::<Vec<_>>());
+    let timezone_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| v.customer.timezone.as_str()).collect::<Vec<_>>());
+    let status_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| format!("{:?}", v.customer.status)).collect::<Vec<_>>());
+    let version_array = UInt32Array::from_slice(versions.iter().map(|v| v.version).collect::<Vec<_>>());
+    let from_array = Utf8Array::<i32>::from_slice(versions.iter().map(|v| v.effective_from.to_rfc3339()).collect::<Vec<_>>());
+    let to_array = Utf8Array::<i32>::from(versions.iter().map(|v| v.effective_to.map(|d| d.to_rfc3339())).collect::<Vec<_>>());
+    let current_array = BooleanArray::from_slice(versions.iter().map(|v| v.effective_to.is_none()).collect::<Vec<_>>());
+
+    return Chunk::new(vec![
+        Arc::new(id_array),
+        Arc::new(name_array),
+        Arc::new(email_array),
+        Arc::new(address_array),
+        Arc::new(country_array),
+        Arc::new(timezone_array),
+        Arc::new(status_array),
+        Arc::new(version_array),
+        Arc::new(from_array),
+        Arc::new(to_array),
+        Arc::new(current_array),
     ]);
 }
 
+pub fn write_customer_history(history: &CustomerHistory, output_path: &str) -> arrow2::error::Result<()> {
+    return write_chunk(get_customer_history_schema(), customer_history_to_chunk(history), output_path);
+}
+
+pub fn get_fx_rate_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("date", DataType::Utf8, false),
+        Field::new("currency", DataType::Utf8, false),
+        Field::new("units_per_usd", DataType::Float64, false),
+    ]);
+}
+
+pub fn fx_rates_to_chunk(fx: &FxTable) -> Chunk<Arc<dyn Array>> {
+    let rows: Vec<_> = fx.rows().collect();
+    let date_array = Utf8Array::<i32>::from_slice(rows.iter().map(|(d, _, _)| d.to_string()).collect::<Vec<_>>());
+    let currency_array = Utf8Array::<i32>::from_slice(rows.iter().map(|(_, c, _)| *c).collect::<Vec<_>>());
+    let rate_array = Float64Array::from_slice(rows.iter().map(|(_, _, r)| *r).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(date_array),
+        Arc::new(currency_array),
+        Arc::new(rate_array),
+    ]);
+}
+
+pub fn write_fx_rates(fx: &FxTable, output_path: &str) -> arrow2::error::Result<()> {
+    return write_chunk(get_fx_rate_schema(), fx_rates_to_chunk(fx), output_path);
+}
+
+pub fn get_instrument_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("instrument_id", DataType::Utf8, false),
+        Field::new("customer_id", DataType::Int64, false),
+        Field::new("method", DataType::Utf8, false),
+        Field::new("version", DataType::UInt32, false),
+        Field::new("network", DataType::Utf8, true),
+        Field::new("masked_number", DataType::Utf8, true),
+        Field::new("wallet_provider", DataType::Utf8, true),
+        Field::new("funding_instrument_id", DataType::Utf8, true),
+        Field::new("issued", DataType::Utf8, false),
+        Field::new("expiration", DataType::Utf8, true),
+        Field::new("renewed", DataType::Boolean, false),
+    ]);
+}
+
+/// One row per card version (renewals add versions) and one per wallet.
+/// Wallets follow their funding card, so they have no expiration of their own.
+pub fn instruments_to_chunk(book: &InstrumentBook) -> Chunk<Arc<dyn Array>> {
+    let mut ids = Vec::new();
+    let mut customer_ids = Vec::new();
+    let mut methods = Vec::new();
+    let mut versions = Vec::new();
+    let mut networks = Vec::new();
+    let mut numbers = Vec::new();
+    let mut providers = Vec::new();
+    let mut fundings = Vec::new();
+    let mut issued = Vec::new();
+    let mut expirations = Vec::new();
+    let mut renewed = Vec::new();
+
+    let all: Vec<_> = book.instruments().collect();
+    for instrument in &all {
+        match &instrument.kind {
+            InstrumentKind::Card { versions: cards, .. } => {
+                for (i, version) in cards.iter().enumerate() {
+                    ids.push(instrument.id.clone());
+                    customer_ids.push(instrument.customer_id);
+                    methods.push("card");
+                    versions.push(i as u32 + 1);
+                    networks.push(Some(format!("{:?}", version.card.network)));
+                    numbers.push(Some(version.card.number.clone()));
+                    providers.push(None);
+                    fundings.push(None);
+                    issued.push(version.issued.to_string());
+                    expirations.push(Some(version.card.expiration.to_string()));
+                    renewed.push(i + 1 < cards.len());
+                }
+            }
+            InstrumentKind::Wallet { provider, funding, added } => {
+                let funding_id = {
+                    all.iter()
+                        .filter(|other| other.customer_id == instrument.customer_id)
+                        .nth(*funding)
+                        .map(|other| other.id.clone())
+                };
+                ids.push(instrument.id.clone());
+                customer_ids.push(instrument.customer_id);
+                methods.push("wallet");
+                versions.push(1);
+                networks.push(None);
+                numbers.push(None);
+                providers.push(Some(format!("{provider:?}")));
+                fundings.push(funding_id);
+                issued.push(added.to_string());
+                expirations.push(None);
+                // Wallets are never reissued
+                renewed.push(false);
+            }
+        }
+    }
+
+    return Chunk::new(vec![
+        Arc::new(Utf8Array::<i32>::from_slice(ids)),
+        Arc::new(Int64Array::from_slice(customer_ids)),
+        Arc::new(Utf8Array::<i32>::from_slice(methods)),
+        Arc::new(UInt32Array::from_slice(versions)),
+        Arc::new(Utf8Array::<i32>::from(networks)),
+        Arc::new(Utf8Array::<i32>::from(numbers)),
+        Arc::new(Utf8Array::<i32>::from(providers)),
+        Arc::new(Utf8Array::<i32>::from(fundings)),
+        Arc::new(Utf8Array::<i32>::from_slice(issued)),
+        Arc::new(Utf8Array::<i32>::from(expirations)),
+        Arc::new(BooleanArray::from_slice(renewed)),
+    ]);
+}
+
+pub fn write_instruments(book: &InstrumentBook, output_path: &str) -> arrow2::error::Result<()> {
+    return write_chunk(get_instrument_schema(), instruments_to_chunk(book), output_path);
+}
+
+pub fn get_fraud_label_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("incident_id", DataType::Utf8, false),
+        Field::new("pattern", DataType::Utf8, false),
+        Field::new("customer_id", DataType::Int64, false),
+        Field::new("instrument_id", DataType::Utf8, false),
+        Field::new("incident_start", DataType::Utf8, false),
+    ]);
+}
+
+pub fn fraud_labels_to_chunk(labels: &[FraudLabel]) -> Chunk<Arc<dyn Array>> {
+    let order_id_array = Utf8Array::<i32>::from_slice(labels.iter().map(|l| l.order_id.as_str()).collect::<Vec<_>>());
+    let incident_id_array = Utf8Array::<i32>::from_slice(labels.iter().map(|l| l.incident_id.as_str()).collect::<Vec<_>>());
+    let pattern_array = Utf8Array::<i32>::from_slice(labels.iter().map(|l| format!("{:?}", l.pattern)).collect::<Vec<_>>());
+    let customer_id_array = Int64Array::from_slice(labels.iter().map(|l| l.customer_id).collect::<Vec<_>>());
+    let instrument_id_array = Utf8Array::<i32>::from_slice(labels.iter().map(|l| l.instrument_id.as_str()).collect::<Vec<_>>());
+    let start_array = Utf8Array::<i32>::from_slice(labels.iter().map(|l| l.incident_start.to_rfc3339()).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(order_id_array),
+        Arc::new(incident_id_array),
+        Arc::new(pattern_array),
+        Arc::new(customer_id_array),
+        Arc::new(instrument_id_array),
+        Arc::new(start_array),
+    ]);
+}
+
+impl Tabular for FraudLabel {
+    fn schema() -> Schema {
+        return get_fraud_label_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return fraud_labels_to_chunk(rows);
+    }
+}
+
+pub fn get_inventory_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("date", DataType::Utf8, false),
+        Field::new("product_id", DataType::Int64, false),
+        Field::new("on_hand", DataType::Int64, false),
+        Field::new("on_order", DataType::Int64, false),
+        Field::new("received", DataType::Int64, false),
+        Field::new("sold", DataType::Int64, false),
+        Field::new("backordered", DataType::Int64, false),
+        Field::new("stockout", DataType::Boolean, false),
+    ]);
+}
+
+pub fn inventory_to_chunk(snapshots: &[InventorySnapshot]) -> Chunk<Arc<dyn Array>> {
+    let date_array = Utf8Array::<i32>::from_slice(snapshots.iter().map(|s| s.date.to_string()).collect::<Vec<_>>());
+    let product_id_array = Int64Array::from_slice(snapshots.iter().map(|s| s.product_id).collect::<Vec<_>>());
+    let on_hand_array = Int64Array::from_slice(snapshots.iter().map(|s| s.on_hand).collect::<Vec<_>>());
+    let on_order_array = Int64Array::from_slice(snapshots.iter().map(|s| s.on_order).collect::<Vec<_>>());
+    let received_array = Int64Array::from_slice(snapshots.iter().map(|s| s.received).collect::<Vec<_>>());
+    let sold_array = Int64Array::from_slice(snapshots.iter().map(|s| s.sold).collect::<Vec<_>>());
+    let backordered_array = Int64Array::from_slice(snapshots.iter().map(|s| s.backordered).collect::<Vec<_>>());
+    let stockout_array = BooleanArray::from_slice(snapshots.iter().map(|s| s.stockout).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(date_array),
+        Arc::new(product_id_array),
+        Arc::new(on_hand_array),
+        Arc::new(on_order_array),
+        Arc::new(received_array),
+        Arc::new(sold_array),
+        Arc::new(backordered_array),
+        Arc::new(stockout_array),
+    ]);
+}
+
+impl Tabular for InventorySnapshot {
+    fn schema() -> Schema {
+        return get_inventory_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return inventory_to_chunk(rows);
+    }
+}
+
+pub fn get_shipment_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("shipment_id", DataType::Utf8, false),
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("carrier", DataType::Utf8, false),
+        Field::new("service", DataType::Utf8, false),
+        Field::new("tracking_number", DataType::Utf8, false),
+        Field::new("warehouse", DataType::Utf8, false),
+        Field::new("quantity", DataType::UInt32, false),
+        Field::new("shipped_at", DataType::Utf8, false),
+        Field::new("promised_at", DataType::Utf8, false),
+        Field::new("delivered_at", DataType::Utf8, true),
+        Field::new("exception", DataType::Utf8, true),
+    ]);
+}
+
+pub fn shipments_to_chunk(shipments: &[Shipment]) -> Chunk<Arc<dyn Array>> {
+    let shipment_id_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| s.shipment_id.as_str()).collect::<Vec<_>>());
+    let order_id_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| s.order_id.as_str()).collect::<Vec<_>>());
+    let carrier_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| format!("{:?}", s.carrier)).collect::<Vec<_>>());
+    let service_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| format!("{:?}", s.service)).collect::<Vec<_>>());
+    let tracking_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| s.tracking_number.as_str()).collect::<Vec<_>>());
+    let warehouse_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| s.warehouse).collect::<Vec<_>>());
+    let quantity_array = UInt32Array::from_slice(shipments.iter().map(|s| s.quantity).collect::<Vec<_>>());
+    let shipped_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| s.shipped_at.to_rfc3339()).collect::<Vec<_>>());
+    let promised_array = Utf8Array::<i32>::from_slice(shipments.iter().map(|s| s.promised_at.to_rfc3339()).collect::<Vec<_>>());
+    let delivered_array = Utf8Array::<i32>::from(shipments.iter().map(|s| s.delivered_at.map(|d| d.to_rfc3339())).collect::<Vec<_>>());
+    let exception_array = Utf8Array::<i32>::from(shipments.iter().map(|s| s.exception.map(|e| format!("{e:?}"))).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(shipment_id_array),
+        Arc::new(order_id_array),
+        Arc::new(carrier_array),
+        Arc::new(service_array),
+        Arc::new(tracking_array),
+        Arc::new(warehouse_array),
+        Arc::new(quantity_array),
+        Arc::new(shipped_array),
+        Arc::new(promised_array),
+        Arc::new(delivered_array),
+        Arc::new(exception_array),
+    ]);
+}
+
+impl Tabular for Shipment {
+    fn schema() -> Schema {
+        return get_shipment_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return shipments_to_chunk(rows);
+    }
+}
+
+pub fn get_return_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("return_id", DataType::Utf8, false),
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("shipment_id", DataType::Utf8, false),
+        Field::new("reason", DataType::Utf8, false),
+        Field::new("quantity", DataType::UInt32, false),
+        Field::new("requested_at", DataType::Utf8, false),
+        Field::new("refunded_at", DataType::Utf8, true),
+        Field::new("refund_amount", DataType::Float64, false),
+        Field::new("restocking_fee", DataType::Float64, false),
+        Field::new("full_refund", DataType::Boolean, false),
+    ]);
+}
+
+pub fn returns_to_chunk(returns: &[Return]) -> Chunk<Arc<dyn Array>> {
+    let return_id_array = Utf8Array::<i32>::from_slice(returns.iter().map(|r| r.return_id.as_str()).collect::<Vec<_>>());
+    let order_id_array = Utf8Array::<i32>::from_slice(returns.iter().map(|r| r.order_id.as_str()).collect::<Vec<_>>());
+    let shipment_id_array = Utf8Array::<i32>::from_slice(returns.iter().map(|r| r.shipment_id.as_str()).collect::<Vec<_>>());
+    let reason_array = Utf8Array::<i32>::from_slice(returns.iter().map(|r| format!("{:?}", r.reason)).collect::<Vec<_>>());
+    let quantity_array = UInt32Array::from_slice(returns.iter().map(|r| r.quantity).collect::<Vec<_>>());
+    let requested_array = Utf8Array::<i32>::from_slice(returns.iter().map(|r| r.requested_at.to_rfc3339()).collect::<Vec<_>>());
+    let refunded_array = Utf8Array::<i32>::from(returns.iter().map(|r| r.refunded_at.map(|d| d.to_rfc3339())).collect::<Vec<_>>());
+    let amount_array = Float64Array::from_slice(returns.iter().map(|r| r.refund_amount).collect::<Vec<_>>());
+    let fee_array = Float64Array::from_slice(returns.iter().map(|r| r.restocking_fee).collect::<Vec<_>>());
+    let full_array = BooleanArray::from_slice(returns.iter().map(|r| r.full_refund).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(return_id_array),
+        Arc::new(order_id_array),
+        Arc::new(shipment_id_array),
+        Arc::new(reason_array),
+        Arc::new(quantity_array),
+        Arc::new(requested_array),
+        Arc::new(refunded_array),
+        Arc::new(amount_array),
+        Arc::new(fee_array),
+        Arc::new(full_array),
+    ]);
+}
+
+impl Tabular for Return {
+    fn schema() -> Schema {
+        return get_return_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return returns_to_chunk(rows);
+    }
+}
+
+pub fn get_review_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("review_id", DataType::Utf8, false),
+        Field::new("order_id", DataType::Utf8, false),
+        Field::new("product_id", DataType::Int64, false),
+        Field::new("customer_id", DataType::Int64, false),
+        Field::new("rating", DataType::UInt8, false),
+        Field::new("title", DataType::Utf8, false),
+        Field::new("body", DataType::Utf8, false),
+        Field::new("reviewed_at", DataType::Utf8, false),
+        Field::new("helpful_votes", DataType::UInt32, false),
+    ]);
+}
+
+pub fn reviews_to_chunk(reviews: &[Review]) -> Chunk<Arc<dyn Array>> {
+    let review_id_array = Utf8Array::<i32>::from_slice(reviews.iter().map(|r| r.review_id.as_str()).collect::<Vec<_>>());
+    let order_id_array = Utf8Array::<i32>::from_slice(reviews.iter().map(|r| r.order_id.as_str()).collect::<Vec<_>>());
+    let product_id_array = Int64Array::from_slice(reviews.iter().map(|r| r.product_id).collect::<Vec<_>>());
+    let customer_id_array = Int64Array::from_slice(reviews.iter().map(|r| r.customer_id).collect::<Vec<_>>());
+    let rating_array = UInt8Array::from_slice(reviews.iter().map(|r| r.rating).collect::<Vec<_>>());
+    let title_array = Utf8Array::<i32>::from_slice(reviews.iter().map(|r| r.title.as_str()).collect::<Vec<_>>());
+    let body_array = Utf8Array::<i32>::from_slice(reviews.iter().map(|r| r.body.as_str()).collect::<Vec<_>>());
+    let reviewed_array = Utf8Array::<i32>::from_slice(reviews.iter().map(|r| r.reviewed_at.to_rfc3339()).collect::<Vec<_>>());
+    let votes_array = UInt32Array::from_slice(reviews.iter().map(|r| r.helpful_votes).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(review_id_array),
+        Arc::new(order_id_array),
+        Arc::new(product_id_array),
+        Arc::new(customer_id_array),
+        Arc::new(rating_array),
+        Arc::new(title_array),
+        Arc::new(body_array),
+        Arc::new(reviewed_array),
+        Arc::new(votes_array),
+    ]);
+}
+
+impl Tabular for Review {
+    fn schema() -> Schema {
+        return get_review_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return reviews_to_chunk(rows);
+    }
+}
+
+pub fn get_session_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("session_id", DataType::Utf8, false),
+        Field::new("customer_id", DataType::Int64, true),
+        Field::new("device", DataType::Utf8, false),
+        Field::new("channel", DataType::Utf8, false),
+        Field::new("started_at", DataType::Utf8, false),
+        Field::new("ended_at", DataType::Utf8, false),
+        Field::new("order_id", DataType::Utf8, true),
+        Field::new("event_count", DataType::UInt32, false),
+    ]);
+}
+
+pub fn sessions_to_chunk(sessions: &[Session]) -> Chunk<Arc<dyn Array>> {
+    let session_id_array = Utf8Array::<i32>::from_slice(sessions.iter().map(|s| s.session_id.as_str()).collect::<Vec<_>>());
+    let customer_id_array = Int64Array::from(sessions.iter().map(|s| s.customer_id).collect::<Vec<_>>());
+    let device_array = Utf8Array::<i32>::from_slice(sessions.iter().map(|s| format!("{:?}", s.device)).collect::<Vec<_>>());
+    let channel_array = Utf8Array::<i32>::from_slice(sessions.iter().map(|s| format!("{:?}", s.channel)).collect::<Vec<_>>());
+    let started_array = Utf8Array::<i32>::from_slice(sessions.iter().map(|s| s.started_at.to_rfc3339()).collect::<Vec<_>>());
+    let ended_array = Utf8Array::<i32>::from_slice(sessions.iter().map(|s| s.ended_at.to_rfc3339()).collect::<Vec<_>>());
+    let order_id_array = Utf8Array::<i32>::from(sessions.iter().map(|s| s.order_id.as_deref()).collect::<Vec<_>>());
+    let event_count_array = UInt32Array::from_slice(sessions.iter().map(|s| s.event_count).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(session_id_array),
+        Arc::new(customer_id_array),
+        Arc::new(device_array),
+        Arc::new(channel_array),
+        Arc::new(started_array),
+        Arc::new(ended_array),
+        Arc::new(order_id_array),
+        Arc::new(event_count_array),
+    ]);
+}
+
+impl Tabular for Session {
+    fn schema() -> Schema {
+        return get_session_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return sessions_to_chunk(rows);
+    }
+}
+
+pub fn get_session_event_schema() -> Schema {
+    return Schema::from(vec![
+        Field::new("event_id", DataType::Utf8, false),
+        Field::new("session_id", DataType::Utf8, false),
+        Field::new("sequence", DataType::UInt32, false),
+        Field::new("event_type", DataType::Utf8, false),
+        Field::new("page", DataType::Utf8, false),
+        Field::new("product_id", DataType::Int64, true),
+        Field::new("occurred_at", DataType::Utf8, false),
+    ]);
+}
+
+pub fn session_events_to_chunk(events: &[SessionEvent]) -> Chunk<Arc<dyn Array>> {
+    let event_id_array = Utf8Array::<i32>::from_slice(events.iter().map(|e| e.event_id.as_str()).collect::<Vec<_>>());
+    let session_id_array = Utf8Array::<i32>::from_slice(events.iter().map(|e| e.session_id.as_str()).collect::<Vec<_>>());
+    let sequence_array = UInt32Array::from_slice(events.iter().map(|e| e.sequence).collect::<Vec<_>>());
+    let event_type_array = Utf8Array::<i32>::from_slice(events.iter().map(|e| format!("{:?}", e.event_type)).collect::<Vec<_>>());
+    let page_array = Utf8Array::<i32>::from_slice(events.iter().map(|e| e.page.as_str()).collect::<Vec<_>>());
+    let product_id_array = Int64Array::from(events.iter().map(|e| e.product_id).collect::<Vec<_>>());
+    let occurred_array = Utf8Array::<i32>::from_slice(events.iter().map(|e| e.occurred_at.to_rfc3339()).collect::<Vec<_>>());
+    return Chunk::new(vec![
+        Arc::new(event_id_array),
+        Arc::new(session_id_array),
+        Arc::new(sequence_array),
+        Arc::new(event_type_array),
+        Arc::new(page_array),
+        Arc::new(product_id_array),
+        Arc::new(occurred_array),
+    ]);
+}
+
+impl Tabular for SessionEvent {
+    fn schema() -> Schema {
+        return get_session_event_schema();
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return session_events_to_chunk(rows);
+    }
+}
+
 pub fn write_parquet(orders: &[Order], output_path: &str) -> arrow2::error::Result<()> {
-    let schema = get_order_schema();
-    let chunk = orders_to_chunk(orders);
+    return write_chunk(get_order_schema(), orders_to_chunk(orders)?, output_path);
+}
+
+// ------------------------ Sinks ------------------------
+
+/// Somewhere to put a generated dataset
+pub trait Sink {
+    fn write(&mut self, dataset: &Dataset) -> error::Result<()>;
+}
+
+/// Writes every dataset as Parquet files in one directory, the way the
+/// processor expects them. A file that fails to write is reported and the
+/// rest are still written, then the first failure is returned.
+pub struct ParquetSink {
+    pub output_dir: String,
+    /// Write the orders as a star schema instead of data.parquet
+    pub star: bool,
+    pub orphan_rate: f64,
+    pub late_dimension_rate: f64,
+    written: Vec<PathBuf>,
+    integrity: Option<IntegrityCounts>,
+}
+
+impl ParquetSink {
+    pub fn new(output_dir: &str) -> ParquetSink {
+        return ParquetSink {
+            output_dir: output_dir.to_string(),
+            star: false,
+            orphan_rate: 0.0,
+            late_dimension_rate: 0.0,
+            written: Vec::new(),
+            integrity: None,
+        };
+    }
+
+    pub fn from_config(config: &Config) -> ParquetSink {
+        return ParquetSink {
+            output_dir: config.output_dir.clone(),
+            star: config.star,
+            orphan_rate: config.orphan_rate,
+            late_dimension_rate: config.late_dimension_rate,
+            written: Vec::new(),
+            integrity: None,
+        };
+    }
+
+    /// Write each table to `<name>.parquet`
+    pub fn write_tables(&mut self, tables: &[Table]) -> error::Result<()> {
+        for table in tables {
+            let path = self.path(&format!("{}.parquet", table.name));
+            write_chunk(table.schema.clone(), table.chunk.clone(), &path)?;
+            self.written.push(PathBuf::from(path));
+        }
+        return Ok(());
+    }
+
+    /// Every file written so far, in order
+    pub fn written(&self) -> &[PathBuf] {
+        return &self.written;
+    }
+
+    /// The violations planted in the last star schema written, if any was
+    pub fn integrity(&self) -> Option<IntegrityCounts> {
+        return self.integrity;
+    }
+
+    /// Write `file` in the output directory, remembering it if it was written
+    fn write_file(
+        &mut self,
+        failures: &mut Failures,
+        what: &str,
+        file: &str,
+        write: impl FnOnce(&str) -> arrow2::error::Result<()>,
+    ) {
+        let path = self.path(file);
+        if failures.check(&format!("write {what}"), write(&path)) {
+            self.written.push(PathBuf::from(path));
+        }
+    }
+
+    fn path(&self, file: &str) -> String {
+        return format!("{}/{file}", self.output_dir);
+    }
+}
+
+impl Sink for ParquetSink {
+    fn write(&mut self, dataset: &Dataset) -> error::Result<()> {
+        let mut failures = Failures::default();
+        self.write_file(&mut failures, "payment instruments", "payment_instruments.parquet", |path| {
+            write_instruments(&dataset.instruments, path)
+        });
+        self.write_file(&mut failures, "shipments", "shipments.parquet", |path| write_table(&dataset.shipments, path));
+        self.write_file(&mut failures, "returns", "returns.parquet", |path| write_table(&dataset.returns, path));
+        self.write_file(&mut failures, "reviews", "reviews.parquet", |path| write_table(&dataset.reviews, path));
+        self.write_file(&mut failures, "FX rates", "fx_rates.parquet", |path| write_fx_rates(&dataset.fx, path));
+        if let Some(labels) = &dataset.fraud_labels {
+            self.write_file(&mut failures, "fraud labels", "fraud_labels.parquet", |path| write_table(labels, path));
+        }
+        if let Some(snapshots) = &dataset.snapshots {
+            self.write_file(&mut failures, "inventory snapshots", "inventory_snapshots.parquet", |path| {
+                write_table(snapshots, path)
+            });
+        }
+        if let Some(clickstream) = &dataset.clickstream {
+            self.write_file(&mut failures, "sessions", "sessions.parquet", |path| write_table(&clickstream.sessions, path));
+            self.write_file(&mut failures, "session events", "session_events.parquet", |path| {
+                write_table(&clickstream.events, path)
+            });
+        }
+        failures.check("write registered tables", self.write_tables(&dataset.tables));
+        if let Some(history) = &dataset.history {
+            self.write_file(&mut failures, "customer history", "customer_history.parquet", |path| {
+                write_customer_history(history, path)
+            });
+        }
+
+        if !self.star {
+            self.write_file(&mut failures, "orders", "data.parquet", |path| write_parquet(&dataset.orders, path));
+            return failures.into_result();
+        }
+        let integrity = {
+            let mut rng = StdRng::seed_from_u64(dataset.seed ^ INTEGRITY_SEED);
+            integrity::plan(&dataset.orders, self.orphan_rate, self.late_dimension_rate, &self.output_dir, &mut rng)
+                .unwrap_or_else(|err| {
+                    failures.check("load the dimensions held back by the last batch", Err(err));
+                    IntegrityPlan::default()
+                })
+        };
+        if failures.check("write star schema", write_star_schema(&dataset.orders, &self.output_dir, &integrity)) {
+            self.written.extend(STAR_TABLES.map(|table| PathBuf::from(self.path(&format!("{table}.parquet")))));
+        }
+        integrity.report();
+        self.integrity = Some(integrity.counts());
+        match integrity.save(&self.output_dir) {
+            Ok(saved) => self.written.extend(saved),
+            Err(err) => _ = failures.check("save held-back dimensions", Err(err)),
+        }
+        return failures.into_result();
+    }
+}
+
+/// Steps that failed so far. Each is reported as it happens; the first
+/// becomes the sink's error once everything else has been written.
+#[derive(Default)]
+struct Failures {
+    first: Option<error::Error>,
+}
+
+impl Failures {
+    /// Whether `result` succeeded
+    fn check<E: Into<error::Error>>(&mut self, what: &str, result: Result<(), E>) -> bool {
+        let Err(err) = result else { return true };
+        let err = err.into();
+        eprintln!("Failed to {what}: {err}");
+        self.first.get_or_insert(err);
+        return false;
+    }
+
+    fn into_result(self) -> error::Result<()> {
+        return match self.first {
+            Some(err) => Err(err),
+            None => Ok(()),
+        };
+    }
+}
+
+// ------------------------ Star schema ------------------------
+
+/// The files `write_star_schema` writes, as `<table>.parquet`
+pub const STAR_TABLES: [&str; 4] = ["customers", "products", "payments", "orders"];
+
+/// The order schema with the customer, product and payment structs replaced
+/// by their keys
+pub fn get_order_fact_schema() -> Schema {
+    let mut fields = get_order_schema().fields;
+    fields.splice(1..4, [
+        Field::new("customer_id", DataType::Int64, false),
+        Field::new("product_id", DataType::Int64, false),
+        Field::new("transaction_id", DataType::Utf8, false),
+    ]);
+    return Schema::from(fields);
+}
+
+/// Order facts keyed to their dimensions, with any orphaned keys from `integrity`
+pub fn order_facts_to_chunk(orders: &[Order], integrity: &IntegrityPlan) -> arrow2::error::Result<Chunk<Arc<dyn Array>>> {
+    let customer_id_array = Int64Array::from_slice(
+        orders
+            .iter()
+            .enumerate()
+            .map(|(i, o)| *integrity.orphan_customers.get(&i).unwrap_or(&o.customer.id))
+            .collect::<Vec<_>>()
+    );
+    let product_id_array = Int64Array::from_slice(
+        orders
+            .iter()
+            .enumerate()
+            .map(|(i, o)| *integrity.orphan_products.get(&i).unwrap_or(&o.product.id))
+            .collect::<Vec<_>>()
+    );
+    let transaction_id_array = Utf8Array::<i32>::from_slice(orders.iter().map(|o| o.payment.transaction_id.as_str()).collect::<Vec<_>>());
+    let mut columns = orders_to_chunk(orders)?.into_arrays();
+    columns.splice(1..4, [
+        Arc::new(customer_id_array) as Arc<dyn Array>,
+        Arc::new(product_id_array),
+        Arc::new(transaction_id_array),
+    ]);
+    return Ok(Chunk::new(columns));
+}
+
+/// Write the orders as a star schema: `customers`, `products` and `payments`
+/// dimension files, and an `orders` fact file that references them by
+/// `customer_id`, `product_id` and `transaction_id`. A customer who appears
+/// in several versions keeps the one from their latest order. Dimension rows
+/// held back by `integrity` are left out, and those it carried over from the
+/// last batch are added as they were then.
+pub fn write_star_schema(orders: &[Order], output_dir: &str, integrity: &IntegrityPlan) -> arrow2::error::Result<()> {
+    let (mut customers, mut products) = integrity::dimension_rows(orders);
+    customers.retain(|id, _| !integrity.late_customers.contains_key(id));
+    products.retain(|id, _| !integrity.late_products.contains_key(id));
+    for customer in &integrity.carried_customers {
+        customers.entry(customer.id).or_insert(customer);
+    }
+    for product in &integrity.carried_products {
+        products.entry(product.id).or_insert(product);
+    }
+    let customers: Vec<&Customer> = customers.into_values().collect();
+    let products: Vec<&Product> = products.into_values().collect();
+    let payments: Vec<&Payment> = orders.iter().map(|o| &o.payment).collect();
+
+    write_struct_table(get_customer_array(&customers), &format!("{output_dir}/customers.parquet"))?;
+    write_struct_table(get_product_array(&products), &format!("{output_dir}/products.parquet"))?;
+    write_struct_table(get_payment_array(&payments), &format!("{output_dir}/payments.parquet"))?;
+    return write_chunk(get_order_fact_schema(), order_facts_to_chunk(orders, integrity)?, &format!("{output_dir}/orders.parquet"));
+}
+
+/// Write a struct column as a table with one column per field
+fn write_struct_table(array: StructArray, output_path: &str) -> arrow2::error::Result<()> {
+    let schema = Schema::from(array.fields().to_vec());
+    return write_chunk(schema, struct_to_chunk(array), output_path);
+}
+
+fn struct_to_chunk(array: StructArray) -> Chunk<Arc<dyn Array>> {
+    let (_, values, _) = array.into_data();
+    return Chunk::new(values.into_iter().map(Arc::from).collect());
+}
+
+// ------------------------ Entity tables ------------------------
+
+/// Write any `Tabular` rows to a Parquet file
+pub fn write_table<T: Tabular>(rows: &[T], output_path: &str) -> arrow2::error::Result<()> {
+    return write_chunk(T::schema(), T::to_chunk(rows), output_path);
+}
+
+impl Tabular for Customer {
+    fn schema() -> Schema {
+        return Schema::from(StructArray::get_fields(&get_customer_type()).to_vec());
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return struct_to_chunk(get_customer_array(&rows.iter().collect::<Vec<_>>()));
+    }
+}
+
+impl Tabular for Product {
+    fn schema() -> Schema {
+        return Schema::from(StructArray::get_fields(&get_product_type()).to_vec());
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return struct_to_chunk(get_product_array(&rows.iter().collect::<Vec<_>>()));
+    }
+}
+
+impl Tabular for Payment {
+    fn schema() -> Schema {
+        return Schema::from(StructArray::get_fields(&get_payment_type()).to_vec());
+    }
+
+    fn to_chunk(rows: &[Self]) -> Chunk<Arc<dyn Array>> {
+        return struct_to_chunk(get_payment_array(&rows.iter().collect::<Vec<_>>()));
+    }
+}
+
+/// Write a single chunk matching `schema` to a Zstd-compressed Parquet file,
+/// replacing `output_path` only once the whole file is written
+pub fn write_chunk(schema: Schema, chunk: Chunk<Arc<dyn Array>>, output_path: &str) -> arrow2::error::Result<()> {
     let options = WriteOptions {
         write_statistics: true,
-        compression: CompressionOptions::Zstd(Some(ZstdLevel::try_new(3).unwrap())),
+        compression: CompressionOptions::Zstd(Some(ZstdLevel::try_new(3)?)),
         version: Version::V2,
         data_pagesize_limit: None,
     };
@@ -185,11 +1115,10 @@ pub fn write_parquet(orders: &[Order], output_path: &str) -> arrow2::error::Resu
             .fields
             .iter()
             .map(|field| {
-                let parquet_type = to_parquet_type(field).unwrap();
-                let leaf_count = to_parquet_leaves(parquet_type).len();
-                vec![Encoding::Plain; leaf_count]
+                let leaf_count = to_parquet_leaves(to_parquet_type(field)?).len();
+                Ok(vec![Encoding::Plain; leaf_count])
             })
-            .collect()
+            .collect::<arrow2::error::Result<_>>()?
     };
     let mut row_groups = RowGroupIterator::try_new(
         std::iter::once(Ok(chunk)),
@@ -197,12 +1126,18 @@ pub fn write_parquet(orders: &[Order], output_path: &str) -> arrow2::error::Resu
         options,
         encodings,
     )?;
-    let file = File::create(output_path)?;
-    let mut writer = FileWriter::try_new(BufWriter::new(file), schema, options)?;
-    for group in &mut row_groups {
-        writer.write(group?)?;
-    }
-    writer.end(None)?;
-    return Ok(());
+    return utils::write_atomic(Path::new(output_path), |file| {
+        let mut writer = FileWriter::try_new(file, schema, options)?;
+        for group in &mut row_groups {
+            writer.write(group?)?;
+        }
+        writer.end(None)?;
+        return Ok(());
+    });
 }
 
+// ------------------------ Static Data ------------------------
+
+/// Mixed into the run's seed for the star schema's planted violations, so
+/// they don't repeat the draws the orders were generated from
+const INTEGRITY_SEED: u64 = 0x5eed_1a7e;
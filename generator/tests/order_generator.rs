@@ -0,0 +1,132 @@
+#![allow(clippy::needless_return)]
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, NaiveTime};
+use generator::clickstream::EventType;
+use generator::discounts::DiscountPolicy;
+use generator::timing::TimeModel;
+use generator::{Dataset, OrderGenerator, OrderStatus};
+
+fn march_2024() -> (NaiveDate, NaiveDate) {
+    return (NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+}
+
+#[test]
+fn generates_the_requested_orders_within_the_period() {
+    let (start, end) = march_2024();
+    let dataset = OrderGenerator::new(2_000).period(start, end).generate().unwrap();
+
+    assert_eq!(dataset.orders.len(), 2_000);
+    let first = start.and_time(NaiveTime::MIN).and_utc();
+    let last = end.succ_opt().unwrap().and_time(NaiveTime::MIN).and_utc();
+    for order in &dataset.orders {
+        assert!(first <= order.date && order.date < last, "order {} is dated {}", order.id, order.date);
+    }
+    assert!(dataset.fraud_labels.is_none());
+    assert_eq!(dataset.fraud_incidents, 0);
+}
+
+#[test]
+fn labels_the_fraud_rate_share_of_orders() {
+    let (start, end) = march_2024();
+    let dataset = OrderGenerator::new(2_000).period(start, end).fraud_rate(0.05).generate().unwrap();
+
+    assert_eq!(dataset.orders.len(), 2_000);
+    let labels = dataset.fraud_labels.expect("fraud was planted");
+    assert_eq!(labels.len(), 100);
+    assert!(dataset.fraud_incidents > 0);
+
+    let order_ids: HashSet<&str> = dataset.orders.iter().map(|o| o.id.as_str()).collect();
+    let labeled: HashSet<&str> = labels.iter().map(|l| l.order_id.as_str()).collect();
+    assert_eq!(labeled.len(), labels.len(), "an order is labeled twice");
+    assert!(labeled.is_subset(&order_ids), "a label names an order that wasn't generated");
+    for label in &labels {
+        let order = dataset.orders.iter().find(|o| o.id == label.order_id).unwrap();
+        assert_eq!(order.customer.id, label.customer_id);
+        assert!(order.date >= label.incident_start);
+    }
+}
+
+#[test]
+fn rejects_an_out_of_range_fraud_rate() {
+    let err = OrderGenerator::new(10).fraud_rate(1.5).generate().err().expect("the rate is invalid");
+    assert_eq!(err.exit_code(), 2);
+}
+
+#[test]
+fn rejects_discount_rates_outside_zero_to_one() {
+    for rate in [1.5, -0.1, f64::NAN] {
+        let mut policy = DiscountPolicy::default();
+        policy.status_rates[0].rate = rate;
+        let err = OrderGenerator::new(10).discounts(policy).generate().err().expect("the rate is invalid");
+        assert_eq!(err.exit_code(), 2, "rate {rate}");
+    }
+}
+
+#[test]
+fn rejects_growth_that_leaves_nothing_to_sample() {
+    for annual_growth in [-1.0, -2.5, f64::NAN, f64::INFINITY] {
+        let time_model = TimeModel { annual_growth, ..TimeModel::default() };
+        let err = OrderGenerator::new(10).time_model(time_model).generate().err().expect("the growth is invalid");
+        assert_eq!(err.exit_code(), 2, "growth {annual_growth}");
+    }
+}
+
+#[test]
+fn the_same_seed_draws_the_same_dataset() {
+    let (start, end) = march_2024();
+    let generate = || OrderGenerator::new(500).period(start, end).fraud_rate(0.05).sessions(0.2).seed(7).generate().unwrap();
+    let (first, second) = (generate(), generate());
+
+    assert_eq!(first.seed, 7);
+    // Every field of every row, so no stage can fall back to an unseeded rng unnoticed
+    let rows = |dataset: &Dataset| -> Vec<String> {
+        let clickstream = dataset.clickstream.as_ref().expect("sessions were simulated");
+        return vec![
+            format!("{:?}", dataset.orders),
+            format!("{:?}", dataset.shipments),
+            format!("{:?}", dataset.returns),
+            format!("{:?}", dataset.reviews),
+            format!("{:?}", clickstream.sessions),
+            format!("{:?}", clickstream.events),
+            format!("{:?}", dataset.fraud_labels),
+        ];
+    };
+    let (first, second) = (rows(&first), rows(&second));
+    for (first, second) in first.iter().zip(&second) {
+        assert!(first == second, "two runs with the same seed differ");
+    }
+    assert!(!first[1].is_empty() && first[1] != "[]", "no shipments to compare");
+}
+
+#[test]
+fn the_conversion_rate_counts_only_orders_that_went_through() {
+    let (start, end) = march_2024();
+    let dataset = OrderGenerator::new(2_000).period(start, end).sessions(0.1).seed(3).generate().unwrap();
+
+    let clickstream = dataset.clickstream.expect("sessions were simulated");
+    let purchases = clickstream.events.iter().filter(|e| e.event_type == EventType::Purchase).count();
+    let failed = clickstream.events.iter().filter(|e| e.event_type == EventType::CheckoutFailed).count();
+    assert!(failed > 0, "no order was cancelled at checkout");
+    let rate = purchases as f64 / clickstream.sessions.len() as f64;
+    assert!((rate - 0.1).abs() < 0.001, "{rate} of the sessions end in a purchase");
+}
+
+#[test]
+fn sessions_end_the_way_their_order_did() {
+    let (start, end) = march_2024();
+    let dataset = OrderGenerator::new(2_000).period(start, end).inventory(true).sessions(0.1).seed(5).generate().unwrap();
+
+    let clickstream = dataset.clickstream.expect("sessions were simulated");
+    let last_events: HashMap<&str, EventType> = {
+        clickstream.events.iter().map(|e| (e.session_id.as_str(), e.event_type)).collect()
+    };
+    let stock_outs = dataset.orders.iter().filter(|o| o.cancel_reason.as_deref() == Some("OutOfStock")).count();
+    assert!(stock_outs > 0, "nothing ran out of stock");
+    for order in &dataset.orders {
+        let session = order.session_id.as_deref().expect("every order has a session");
+        let expected = if matches!(order.status, OrderStatus::Cancelled) { EventType::CheckoutFailed } else { EventType::Purchase };
+        assert_eq!(last_events[session], expected, "order {} is {:?}", order.id, order.status);
+    }
+}
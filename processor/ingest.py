@@ -1,12 +1,37 @@
+import hashlib
+import json
 import os
+import sys
 import duckdb as ddb
 
 
-def create_temp_table():
+def create_temp_table(path):
     with ddb.connect("orders.duckdb") as con:
-        con.execute("CREATE TABLE incoming_orders AS (SELECT * FROM 'data.parquet')")
+        con.execute(f"CREATE TABLE incoming_orders AS (SELECT * FROM '{path}')")
+
+
+def verify(paths):
+    """Check the files against the generator's manifest.json before loading them"""
+    if not os.path.exists("manifest.json"):
+        print("No manifest.json found. Loading without verification...")
+        return
+    with open("manifest.json") as f:
+        entries = {entry["path"]: entry for entry in json.load(f)["files"]}
+    for path in paths:
+        entry = entries.get(path)
+        if entry is None:
+            sys.exit(f"{path} is not listed in manifest.json")
+        with open(path, "rb") as f:
+            digest = hashlib.file_digest(f, "sha256").hexdigest()
+        if os.path.getsize(path) != entry["bytes"] or digest != entry["sha256"]:
+            sys.exit(f"{path} does not match manifest.json; it is incomplete or was modified")
 
 
 if __name__ == "__main__":
     if os.path.exists("data.parquet"):
-        create_temp_table()
+        verify(["data.parquet"])
+        create_temp_table("data.parquet")
+    elif os.path.exists("orders.parquet"):
+        # Order facts from the generator's --star mode
+        verify(["customers.parquet", "products.parquet", "payments.parquet", "orders.parquet"])
+        create_temp_table("orders.parquet")
@@ -1,5 +1,10 @@
+import os
 import duckdb as ddb
 
+# The generator's --star mode writes customers, products, payments and orders
+# as separate files instead of data.parquet
+STAR = not os.path.exists("data.parquet") and os.path.exists("orders.parquet")
+
 with ddb.connect("orders.duckdb") as con:
     con.execute(
         """
@@ -8,6 +13,8 @@ with ddb.connect("orders.duckdb") as con:
             Name VARCHAR,
             Email VARCHAR,
             Address VARCHAR,
+            Country VARCHAR,
+            Timezone VARCHAR,
             Status VARCHAR
         )
     """
@@ -19,7 +26,8 @@ with ddb.connect("orders.duckdb") as con:
             Id BIGINT,
             Name VARCHAR,
             Category VARCHAR,
-            Price DOUBLE
+            Price DOUBLE,
+            Weight DOUBLE
         )
     """
     )
@@ -34,54 +42,460 @@ with ddb.connect("orders.duckdb") as con:
             Payment VARCHAR,
             Status VARCHAR,
             Discount DOUBLE,
+            DiscountReason VARCHAR,
             Quantity INTEGER,
-            Total DOUBLE
+            Subtotal DOUBLE,
+            DiscountAmount DOUBLE,
+            Tax DOUBLE,
+            Shipping DOUBLE,
+            Total DOUBLE,
+            Currency VARCHAR,
+            TotalLocal DOUBLE,
+            TotalUsd DOUBLE,
+            CancelReason VARCHAR,
+            RestockedAt TIMESTAMP,
+            SessionId VARCHAR
         )
     """
     )
 
-    con.execute(
+    if STAR:
+        con.execute(
+            """
+            INSERT INTO customers
+            SELECT
+                id AS Id,
+                name AS Name,
+                email AS Email,
+                address AS Address,
+                country AS Country,
+                timezone AS Timezone,
+                status AS Status
+            FROM 'customers.parquet'
+            WHERE id NOT IN (SELECT Id FROM customers);
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO products
+            SELECT
+                id AS Id,
+                name AS Name,
+                category AS Category,
+                price AS Price,
+                weight AS Weight
+            FROM 'products.parquet'
+            WHERE id NOT IN (SELECT Id FROM products);
+        """
+        )
+    else:
+        con.execute(
+            """
+            INSERT INTO customers
+            SELECT DISTINCT
+                Customer.Id AS Id,
+                Customer.Name AS Name,
+                Customer.Email AS Email,
+                Customer.Address AS Address,
+                Customer.Country AS Country,
+                Customer.Timezone AS Timezone,
+                Customer.Status AS Status
+            FROM incoming_orders
+            WHERE Customer.Id NOT IN (SELECT Id FROM customers);
         """
-        INSERT INTO customers
-        SELECT DISTINCT
-            Customer.Id AS Id,
-            Customer.Name AS Name,
-            Customer.Email AS Email,
-            Customer.Address AS Address,
-            Customer.Status AS Status
-        FROM incoming_orders
-        WHERE Customer.Id NOT IN (SELECT Id FROM customers);
+        )
+
+        con.execute(
+            """
+            INSERT INTO products
+            SELECT DISTINCT
+                Product.Id AS Id,
+                Product.Name AS Name,
+                Product.Category AS Category,
+                Product.Price AS Price,
+                Product.Weight AS Weight
+            FROM incoming_orders
+            WHERE Product.Id NOT IN (SELECT Id FROM products);
+        """
+        )
+
+    # In --star mode the order references its customer, product and payment by key
+    if STAR:
+        order_keys = """
+            o.Customer_Id AS CustomerId,
+            o.Product_Id AS ProductId,
+            o.Date,
+            CAST(struct_pack(
+                transaction_id := p.transaction_id,
+                instrument_id := p.instrument_id,
+                method := p.method,
+                network := p.network,
+                masked_number := p.masked_number,
+                wallet_provider := p.wallet_provider,
+                expiration := p.expiration
+            ) AS VARCHAR) AS Payment,"""
+        payments_join = "LEFT JOIN 'payments.parquet' p ON p.transaction_id = o.Transaction_Id"
+    else:
+        order_keys = """
+            o.Customer.Id AS CustomerId,
+            o.Product.Id AS ProductId,
+            o.Date,
+            o.Payment,"""
+        payments_join = ""
+
+    con.execute(
+        f"""
+        INSERT INTO orders
+        SELECT
+            o.Id,{order_keys}
+            o.Status,
+            o.Discount,
+            o.Discount_Reason AS DiscountReason,
+            o.Quantity,
+            o.Subtotal,
+            o.Discount_Amount AS DiscountAmount,
+            o.Tax,
+            o.Shipping,
+            o.Total,
+            o.Currency,
+            o.Total_Local AS TotalLocal,
+            o.Total_Usd AS TotalUsd,
+            o.Cancel_Reason AS CancelReason,
+            CAST(o.Restocked_At AS TIMESTAMP) AS RestockedAt,
+            o.Session_Id AS SessionId
+        FROM incoming_orders o {payments_join};
     """
     )
 
+    # Keys the batch's orders point at that no dimension has yet; the generator's
+    # --orphans and --late-dimensions options plant them
+    if STAR:
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS integrity_checks (
+                LoadedAt TIMESTAMP,
+                Orders INTEGER,
+                MissingCustomer INTEGER,
+                MissingProduct INTEGER
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO integrity_checks
+            SELECT
+                CURRENT_TIMESTAMP AS LoadedAt,
+                COUNT(*) AS Orders,
+                COUNT(*) FILTER (WHERE o.Customer_Id NOT IN (SELECT Id FROM customers)) AS MissingCustomer,
+                COUNT(*) FILTER (WHERE o.Product_Id NOT IN (SELECT Id FROM products)) AS MissingProduct
+            FROM incoming_orders o;
+        """
+        )
+
     con.execute(
         """
-        INSERT INTO products
-        SELECT DISTINCT
-            Product.Id AS Id,
-            Product.Name AS Name,
-            Product.Category AS Category,
-            Product.Price AS Price
-        FROM incoming_orders
-        WHERE Product.Id NOT IN (SELECT Id FROM products);
+        CREATE TABLE IF NOT EXISTS payment_attempts (
+            OrderId VARCHAR,
+            Attempt INTEGER,
+            TransactionId VARCHAR,
+            InstrumentId VARCHAR,
+            AttemptedAt TIMESTAMP,
+            Approved BOOLEAN,
+            DeclineCode VARCHAR
+        )
     """
     )
 
     con.execute(
         """
-        INSERT INTO orders
+        INSERT INTO payment_attempts
         SELECT
-            o.Id,
-            o.Customer.Id AS CustomerId,
-            o.Product.Id AS ProductId,
-            o.Date,
-            o.Payment,
-            o.Status,
-            o.Discount,
-            o.Quantity,
-            o.Total
-        FROM incoming_orders o;
+            Id AS OrderId,
+            i AS Attempt,
+            Attempts[i].Transaction_Id AS TransactionId,
+            Attempts[i].Instrument_Id AS InstrumentId,
+            CAST(Attempts[i].Attempted_At AS TIMESTAMP) AS AttemptedAt,
+            Attempts[i].Approved AS Approved,
+            Attempts[i].Decline_Code AS DeclineCode
+        FROM (
+            SELECT Id, Attempts, generate_subscripts(Attempts, 1) AS i
+            FROM incoming_orders
+        );
     """
     )
 
+    # Written by the generator's --scd mode; each batch carries the full history
+    if os.path.exists("customer_history.parquet"):
+        con.execute(
+            """
+            CREATE OR REPLACE TABLE customer_history AS
+            SELECT
+                id AS Id,
+                name AS Name,
+                email AS Email,
+                address AS Address,
+                country AS Country,
+                timezone AS Timezone,
+                status AS Status,
+                version AS Version,
+                CAST(effective_from AS TIMESTAMP) AS EffectiveFrom,
+                CAST(effective_to AS TIMESTAMP) AS EffectiveTo,
+                is_current AS IsCurrent
+            FROM 'customer_history.parquet';
+        """
+        )
+
+    # Daily rates for the simulated period; a given day has the same rate in every batch
+    if os.path.exists("fx_rates.parquet"):
+        con.execute(
+            """
+            CREATE OR REPLACE TABLE fx_rates AS
+            SELECT
+                CAST(date AS DATE) AS Date,
+                currency AS Currency,
+                units_per_usd AS UnitsPerUsd
+            FROM 'fx_rates.parquet';
+        """
+        )
+
+    # Every card version and wallet; identical in every batch
+    if os.path.exists("payment_instruments.parquet"):
+        con.execute(
+            """
+            CREATE OR REPLACE TABLE payment_instruments AS
+            SELECT
+                instrument_id AS InstrumentId,
+                customer_id AS CustomerId,
+                method AS Method,
+                version AS Version,
+                network AS Network,
+                masked_number AS MaskedNumber,
+                wallet_provider AS WalletProvider,
+                funding_instrument_id AS FundingInstrumentId,
+                CAST(issued AS DATE) AS Issued,
+                CAST(expiration AS DATE) AS Expiration,
+                renewed AS Renewed
+            FROM 'payment_instruments.parquet';
+        """
+        )
+
+    # Packages for the batch's orders; they accumulate with the orders
+    if os.path.exists("shipments.parquet"):
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS shipments (
+                ShipmentId VARCHAR,
+                OrderId VARCHAR,
+                Carrier VARCHAR,
+                Service VARCHAR,
+                TrackingNumber VARCHAR,
+                Warehouse VARCHAR,
+                Quantity INTEGER,
+                ShippedAt TIMESTAMP,
+                PromisedAt TIMESTAMP,
+                DeliveredAt TIMESTAMP,
+                Exception VARCHAR
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO shipments
+            SELECT
+                shipment_id AS ShipmentId,
+                order_id AS OrderId,
+                carrier AS Carrier,
+                service AS Service,
+                tracking_number AS TrackingNumber,
+                warehouse AS Warehouse,
+                quantity AS Quantity,
+                CAST(shipped_at AS TIMESTAMP) AS ShippedAt,
+                CAST(promised_at AS TIMESTAMP) AS PromisedAt,
+                CAST(delivered_at AS TIMESTAMP) AS DeliveredAt,
+                exception AS Exception
+            FROM 'shipments.parquet'
+            WHERE order_id IN (SELECT Id FROM incoming_orders);
+        """
+        )
+
+    # Returns and refunds for the batch's orders; they accumulate with the orders
+    if os.path.exists("returns.parquet"):
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS returns (
+                ReturnId VARCHAR,
+                OrderId VARCHAR,
+                ShipmentId VARCHAR,
+                Reason VARCHAR,
+                Quantity INTEGER,
+                RequestedAt TIMESTAMP,
+                RefundedAt TIMESTAMP,
+                RefundAmount DOUBLE,
+                RestockingFee DOUBLE,
+                FullRefund BOOLEAN
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO returns
+            SELECT
+                return_id AS ReturnId,
+                order_id AS OrderId,
+                shipment_id AS ShipmentId,
+                reason AS Reason,
+                quantity AS Quantity,
+                CAST(requested_at AS TIMESTAMP) AS RequestedAt,
+                CAST(refunded_at AS TIMESTAMP) AS RefundedAt,
+                refund_amount AS RefundAmount,
+                restocking_fee AS RestockingFee,
+                full_refund AS FullRefund
+            FROM 'returns.parquet'
+            WHERE order_id IN (SELECT Id FROM incoming_orders);
+        """
+        )
+
+    # Product reviews for the batch's orders; they accumulate with the orders
+    if os.path.exists("reviews.parquet"):
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS reviews (
+                ReviewId VARCHAR,
+                OrderId VARCHAR,
+                ProductId INTEGER,
+                CustomerId INTEGER,
+                Rating INTEGER,
+                Title VARCHAR,
+                Body VARCHAR,
+                ReviewedAt TIMESTAMP,
+                HelpfulVotes INTEGER
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO reviews
+            SELECT
+                review_id AS ReviewId,
+                order_id AS OrderId,
+                product_id AS ProductId,
+                customer_id AS CustomerId,
+                rating AS Rating,
+                title AS Title,
+                body AS Body,
+                CAST(reviewed_at AS TIMESTAMP) AS ReviewedAt,
+                helpful_votes AS HelpfulVotes
+            FROM 'reviews.parquet'
+            WHERE order_id IN (SELECT Id FROM incoming_orders);
+        """
+        )
+
+    # Written by the generator's --sessions mode; each batch brings its own sessions
+    if os.path.exists("sessions.parquet"):
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS sessions (
+                SessionId VARCHAR,
+                CustomerId BIGINT,
+                Device VARCHAR,
+                Channel VARCHAR,
+                StartedAt TIMESTAMP,
+                EndedAt TIMESTAMP,
+                OrderId VARCHAR,
+                EventCount INTEGER
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO sessions
+            SELECT
+                session_id AS SessionId,
+                customer_id AS CustomerId,
+                device AS Device,
+                channel AS Channel,
+                CAST(started_at AS TIMESTAMP) AS StartedAt,
+                CAST(ended_at AS TIMESTAMP) AS EndedAt,
+                order_id AS OrderId,
+                event_count AS EventCount
+            FROM 'sessions.parquet';
+        """
+        )
+
+    if os.path.exists("session_events.parquet"):
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS session_events (
+                EventId VARCHAR,
+                SessionId VARCHAR,
+                Sequence INTEGER,
+                EventType VARCHAR,
+                Page VARCHAR,
+                ProductId BIGINT,
+                OccurredAt TIMESTAMP
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO session_events
+            SELECT
+                event_id AS EventId,
+                session_id AS SessionId,
+                sequence AS Sequence,
+                event_type AS EventType,
+                page AS Page,
+                product_id AS ProductId,
+                CAST(occurred_at AS TIMESTAMP) AS OccurredAt
+            FROM 'session_events.parquet';
+        """
+        )
+
+    # Written by the generator's --inventory mode; each batch simulates the whole period
+    if os.path.exists("inventory_snapshots.parquet"):
+        con.execute(
+            """
+            CREATE OR REPLACE TABLE inventory_snapshots AS
+            SELECT
+                CAST(date AS DATE) AS Date,
+                product_id AS ProductId,
+                on_hand AS OnHand,
+                on_order AS OnOrder,
+                received AS Received,
+                sold AS Sold,
+                backordered AS Backordered,
+                stockout AS Stockout
+            FROM 'inventory_snapshots.parquet';
+        """
+        )
+
+    # Written by the generator's --fraud mode; labels accumulate with their orders
+    if os.path.exists("fraud_labels.parquet"):
+        con.execute(
+            """
+            CREATE TABLE IF NOT EXISTS fraud_labels (
+                OrderId VARCHAR,
+                IncidentId VARCHAR,
+                Pattern VARCHAR,
+                CustomerId BIGINT,
+                InstrumentId VARCHAR,
+                IncidentStart TIMESTAMP
+            )
+        """
+        )
+        con.execute(
+            """
+            INSERT INTO fraud_labels
+            SELECT
+                order_id AS OrderId,
+                incident_id AS IncidentId,
+                pattern AS Pattern,
+                customer_id AS CustomerId,
+                instrument_id AS InstrumentId,
+                CAST(incident_start AS TIMESTAMP) AS IncidentStart
+            FROM 'fraud_labels.parquet'
+            WHERE order_id IN (SELECT Id FROM incoming_orders);
+        """
+        )
+
     con.execute("DROP TABLE IF EXISTS incoming_orders")
@@ -12,7 +12,7 @@ def save_customer_anomalies(con):
             COUNT(*) AS AnomalyCount
         FROM order_anomalies o
         JOIN customers c ON o.CustomerId = c.Id
-        WHERE o.CancelReason <> 'BadCard'
+        WHERE o.CancelReason = 'BadId'
         GROUP BY o.CustomerId, c.Name
         ORDER BY AnomalyCount DESC;
         """
@@ -76,18 +76,232 @@ def save_anomalies(con):
     con.execute(
         """
         CREATE TABLE IF NOT EXISTS order_anomalies AS
-        SELECT
-            *,
-            CASE 
-                WHEN LENGTH(Id) != 36 THEN 'BadId'
-                ELSE 'BadCard'
-            END AS CancelReason
+        SELECT *
         FROM orders
         WHERE Status = 'Cancelled';
         """
     )
 
 
+def save_payment_declines(con):
+    con.execute("DROP TABLE IF EXISTS payment_declines")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS payment_declines AS
+        SELECT
+            a.DeclineCode,
+            COUNT(*) AS Attempts,
+            COUNT(DISTINCT a.OrderId) AS Orders,
+            COUNT(DISTINCT a.OrderId) FILTER (WHERE o.CancelReason IS NULL) AS RecoveredOrders
+        FROM payment_attempts a
+        JOIN orders o ON a.OrderId = o.Id
+        WHERE NOT a.Approved
+        GROUP BY a.DeclineCode
+        ORDER BY Attempts DESC;
+        """
+    )
+
+
+def save_fraud_label_summary(con):
+    con.execute("DROP TABLE IF EXISTS customer_fraud_labels")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS customer_fraud_labels AS
+        SELECT
+            c.Id AS CustomerId,
+            c.Name AS CustomerName,
+            COALESCE(a.AnomalyCount, 0) AS AnomalyCount,
+            COUNT(f.OrderId) AS FraudOrders,
+            COUNT(DISTINCT f.IncidentId) AS FraudIncidents,
+            STRING_AGG(DISTINCT f.Pattern, ', ') AS Patterns
+        FROM customers c
+        LEFT JOIN customer_anomalies a ON a.CustomerId = c.Id
+        LEFT JOIN fraud_labels f ON f.CustomerId = c.Id
+        GROUP BY c.Id, c.Name, a.AnomalyCount
+        ORDER BY FraudOrders DESC;
+        """
+    )
+
+
+def save_product_stockouts(con):
+    con.execute("DROP TABLE IF EXISTS product_stockouts")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS product_stockouts AS
+        WITH days AS (
+            SELECT
+                ProductId,
+                COUNT(*) FILTER (WHERE Stockout) AS StockoutDays,
+                SUM(Received) AS UnitsReceived
+            FROM inventory_snapshots
+            GROUP BY ProductId
+        ),
+        affected AS (
+            SELECT
+                ProductId,
+                COUNT(*) FILTER (WHERE CancelReason = 'OutOfStock') AS CancelledOrders,
+                COUNT(*) FILTER (WHERE RestockedAt IS NOT NULL OR Status = 'Backordered') AS BackorderedOrders
+            FROM orders
+            GROUP BY ProductId
+        )
+        SELECT
+            p.Id AS ProductId,
+            p.Name AS ProductName,
+            d.StockoutDays,
+            d.UnitsReceived,
+            COALESCE(a.CancelledOrders, 0) AS CancelledOrders,
+            COALESCE(a.BackorderedOrders, 0) AS BackorderedOrders
+        FROM products p
+        JOIN days d ON d.ProductId = p.Id
+        LEFT JOIN affected a ON a.ProductId = p.Id
+        ORDER BY d.StockoutDays DESC;
+        """
+    )
+
+
+def save_fulfillment_sla(con):
+    con.execute("DROP TABLE IF EXISTS fulfillment_sla")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS fulfillment_sla AS
+        SELECT
+            s.Carrier,
+            s.Service,
+            COUNT(*) AS Shipments,
+            COUNT(*) FILTER (WHERE s.DeliveredAt IS NOT NULL) AS Delivered,
+            1.0 * COUNT(*) FILTER (WHERE s.DeliveredAt <= s.PromisedAt)
+                / NULLIF(COUNT(*) FILTER (WHERE s.DeliveredAt IS NOT NULL), 0) AS OnTimeRate,
+            AVG(EPOCH(s.ShippedAt - o.Date)) / 3600 AS AvgHoursToShip,
+            AVG(EPOCH(s.DeliveredAt - s.ShippedAt)) / 86400 AS AvgTransitDays,
+            COUNT(*) FILTER (WHERE s.Exception IS NOT NULL) AS Exceptions,
+            COUNT(*) FILTER (WHERE s.Exception = 'Lost') AS Lost
+        FROM shipments s
+        JOIN orders o ON s.OrderId = o.Id
+        GROUP BY s.Carrier, s.Service
+        ORDER BY Shipments DESC;
+        """
+    )
+
+
+def save_product_return_reasons(con):
+    con.execute("DROP TABLE IF EXISTS product_return_reasons")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS product_return_reasons AS
+        SELECT
+            o.ProductId,
+            p.Name AS ProductName,
+            p.Category,
+            r.Reason,
+            COUNT(*) AS Returns,
+            SUM(r.Quantity) AS UnitsReturned,
+            SUM(r.RefundAmount) AS Refunded,
+            SUM(r.RestockingFee) AS RestockingFees,
+            COUNT(*) FILTER (WHERE NOT r.FullRefund) AS PartialRefunds
+        FROM returns r
+        JOIN orders o ON r.OrderId = o.Id
+        JOIN products p ON o.ProductId = p.Id
+        GROUP BY o.ProductId, p.Name, p.Category, r.Reason
+        ORDER BY o.ProductId, Returns DESC;
+        """
+    )
+
+
+def save_product_ratings(con):
+    con.execute("DROP TABLE IF EXISTS product_ratings")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS product_ratings AS
+        SELECT
+            r.ProductId,
+            p.Name AS ProductName,
+            p.Category,
+            COUNT(*) AS Reviews,
+            AVG(r.Rating) AS AvgRating,
+            AVG(r.Rating) FILTER (WHERE ret.OrderId IS NULL) AS AvgRatingKept,
+            AVG(r.Rating) FILTER (WHERE ret.OrderId IS NOT NULL) AS AvgRatingReturned,
+            COUNT(*) FILTER (WHERE r.Rating <= 2) AS LowRatings,
+            AVG(LENGTH(r.Body)) AS AvgBodyLength
+        FROM reviews r
+        JOIN products p ON r.ProductId = p.Id
+        LEFT JOIN (SELECT DISTINCT OrderId FROM returns) ret ON r.OrderId = ret.OrderId
+        GROUP BY r.ProductId, p.Name, p.Category
+        ORDER BY AvgRating;
+        """
+    )
+
+
+def save_conversion_funnel(con):
+    con.execute("DROP TABLE IF EXISTS conversion_funnel")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS conversion_funnel AS
+        WITH reached AS (
+            SELECT
+                s.SessionId,
+                s.Channel,
+                s.Device,
+                BOOL_OR(e.EventType = 'ProductView') AS ViewedProduct,
+                BOOL_OR(e.EventType = 'AddToCart') AS AddedToCart,
+                BOOL_OR(e.EventType = 'Checkout') AS StartedCheckout,
+                BOOL_OR(e.EventType = 'Purchase') AS Purchased,
+                BOOL_OR(e.EventType = 'CheckoutFailed') AS CheckoutFailed
+            FROM sessions s
+            JOIN session_events e ON e.SessionId = s.SessionId
+            GROUP BY s.SessionId, s.Channel, s.Device
+        )
+        SELECT
+            r.Channel,
+            r.Device,
+            COUNT(*) AS Sessions,
+            COUNT(*) FILTER (WHERE r.ViewedProduct) AS ProductViews,
+            COUNT(*) FILTER (WHERE r.AddedToCart) AS AddedToCart,
+            COUNT(*) FILTER (WHERE r.StartedCheckout) AS StartedCheckout,
+            COUNT(*) FILTER (WHERE r.CheckoutFailed) AS CheckoutFailed,
+            COUNT(*) FILTER (WHERE r.Purchased) AS Purchased,
+            COUNT(o.Id) FILTER (WHERE o.Status NOT IN ('Cancelled', 'Refunded')) AS KeptOrders,
+            1.0 * COUNT(*) FILTER (WHERE r.Purchased) / COUNT(*) AS ConversionRate
+        FROM reached r
+        LEFT JOIN orders o ON o.SessionId = r.SessionId
+        GROUP BY r.Channel, r.Device
+        ORDER BY Sessions DESC;
+        """
+    )
+
+
+def save_referential_integrity(con):
+    con.execute("DROP TABLE IF EXISTS referential_integrity")
+    con.execute(
+        """
+        CREATE TABLE IF NOT EXISTS referential_integrity AS
+        SELECT
+            'Customer' AS Dimension,
+            CustomerId AS MissingId,
+            COUNT(*) AS Orders,
+            MIN(Date) AS FirstOrder
+        FROM orders
+        WHERE CustomerId NOT IN (SELECT Id FROM customers)
+        GROUP BY CustomerId
+        UNION ALL
+        SELECT
+            'Product' AS Dimension,
+            ProductId AS MissingId,
+            COUNT(*) AS Orders,
+            MIN(Date) AS FirstOrder
+        FROM orders
+        WHERE ProductId NOT IN (SELECT Id FROM products)
+        GROUP BY ProductId
+        ORDER BY Orders DESC;
+        """
+    )
+
+
+def has_table(con, name):
+    return con.execute(
+        "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = ?", [name]
+    ).fetchone()[0] > 0
+
+
 def save_most_popular_product(con):
     con.execute("DROP TABLE IF EXISTS top_product")
     con.execute(
@@ -116,14 +330,36 @@ def save_most_popular_product(con):
 with ddb.connect("orders.duckdb") as con:
     print("Saving anomalies...")
     save_anomalies(con)
+    print("Saving payment declines...")
+    save_payment_declines(con)
     print("Saving most popular products...")
     save_most_popular_product(con)
     print("Saving monthly order volume...")
     save_monthly_order_volume(con)
     print("Saving number of customer anomalies...")
     save_customer_anomalies(con)
+    if has_table(con, "fraud_labels"):
+        print("Saving fraud labels per customer...")
+        save_fraud_label_summary(con)
+    if has_table(con, "shipments"):
+        print("Saving fulfillment SLAs...")
+        save_fulfillment_sla(con)
+    if has_table(con, "inventory_snapshots"):
+        print("Saving product stock-outs...")
+        save_product_stockouts(con)
     print("Saving product return rates...")
     save_product_return_rates(con)
+    if has_table(con, "returns"):
+        print("Saving product return reasons...")
+        save_product_return_reasons(con)
+    if has_table(con, "reviews") and has_table(con, "returns"):
+        print("Saving product ratings...")
+        save_product_ratings(con)
+    if has_table(con, "sessions") and has_table(con, "session_events"):
+        print("Saving conversion funnel...")
+        save_conversion_funnel(con)
+    print("Saving referential integrity violations...")
+    save_referential_integrity(con)
     print("Saving top spending customers...")
     save_top_customers(con)
     print("Done! Your data is ready to view in 'orders.duckdb'!")
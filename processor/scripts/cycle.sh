@@ -16,25 +16,48 @@ while true; do
   echo ""
   echo "=== Iteration $((COUNT + 1)) ==="
 
+  # Arguments after the batch size are forwarded to the generator
   if [[ -n $2 ]]; then
-    ./gen_data.sh $2
+    ./gen_data.sh $2 "${@:3}"
   else 
     ./gen_data.sh
   fi
+  STATUS=$?
+
+  # Bad arguments fail every iteration the same way
+  if [[ $STATUS -eq 2 ]]; then
+    echo "Stopping: fix the generator arguments and try again."
+    exit $STATUS
+  fi
 
   cd ..
-  if [[ -f "data.parquet" ]]; then
+  if [[ $STATUS -ne 0 ]]; then
+    echo "Generation failed. Skipping this batch..."
+  elif [[ -f "data.parquet" || -f "orders.parquet" ]]; then
     echo "Ingesting data..."
-    python3 ingest.py
-    echo "Done!"
-    echo "Normalizing data..."
-    python3 normalize.py
-    echo "Done!"
+    if python3 ingest.py; then
+      echo "Done!"
+      echo "Normalizing data..."
+      python3 normalize.py
+      echo "Done!"
+    else
+      echo "Ingest failed. Skipping normalization..."
+    fi
     UUID=$(uuidgen)
-    mv data.parquet "data-$UUID.parquet"
-    echo "Data moved to 'data-$UUID.parquet'."
+    if [[ -f "manifest.json" ]]; then
+      mv manifest.json "manifest-$UUID.json"
+    fi
+    if [[ -f "data.parquet" ]]; then
+      mv data.parquet "data-$UUID.parquet"
+      echo "Data moved to 'data-$UUID.parquet'."
+    else
+      for TABLE in customers products payments orders; do
+        mv "$TABLE.parquet" "$TABLE-$UUID.parquet"
+      done
+      echo "Star schema moved to '<table>-$UUID.parquet'."
+    fi
   else
-    echo "No data.parquet found. Skipping..."
+    echo "No data.parquet or orders.parquet found. Skipping..."
   fi
   cd scripts
 
@@ -2,16 +2,24 @@
 
 cd ../../generator
 
-if [[ -n $1 ]]; then
-  ./generator $1 &
-else
-  ./generator &
-fi
+# Any arguments (order count, generator flags) are passed straight through
+./generator "$@" &
 RUST_PS=$!
 
 cd ../processor/scripts
 
 echo "Generating data..."
 wait "$RUST_PS"
-echo "Done!"
+STATUS=$?
+
+# The generator's exit code says what went wrong
+case $STATUS in
+  0) echo "Done!" ;;
+  2) echo "Generator rejected its arguments or config files." ;;
+  3) echo "Generator could not produce the requested data." ;;
+  4) echo "Generator failed to read or write a file." ;;
+  5) echo "Generator failed to encode a Parquet file." ;;
+  *) echo "Generator exited with status $STATUS." ;;
+esac
+exit $STATUS
 
@@ -3,8 +3,15 @@
 cd ..
 echo "Removing all 'data*.parquet*' files..."
 rm -f data*.parquet*
+rm -f customers*.parquet products*.parquet payments*.parquet orders*.parquet late_dimensions.json
+rm -f customer_history.parquet fx_rates.parquet payment_instruments.parquet fraud_labels.parquet \
+    inventory_snapshots.parquet shipments.parquet returns.parquet reviews.parquet \
+    sessions.parquet session_events.parquet
+rm -f .*.tmp manifest*.json
 echo "Removing 'orders.duckdb'..."
 rm -f orders.duckdb
+echo "Removing CDC records in 'cdc/'..."
+rm -rf cdc
 echo "Done!"
 cd scripts
 